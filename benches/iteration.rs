@@ -0,0 +1,55 @@
+use std::{hint::black_box, time::Instant};
+
+use dces::component::{AppendComponents, ArchetypeComponentStore, TypeComponentBuilder};
+use dces::prelude::*;
+
+const ENTITIES: u32 = 10_000;
+const ITERATIONS: u32 = 100;
+
+struct Size(u32);
+
+struct Position(u32);
+
+fn components(id: u32) -> <ComponentStore as AppendComponents>::Components {
+    let mut builder = TypeComponentBuilder::new().with(Size(id));
+    if id.is_multiple_of(2) {
+        builder = builder.with(Position(id));
+    }
+    builder.build()
+}
+
+fn bench(name: &str, mut f: impl FnMut() -> u32) {
+    let start = Instant::now();
+    let mut sum = 0_u32;
+    for _ in 0..ITERATIONS {
+        sum = sum.wrapping_add(f());
+    }
+    black_box(sum);
+
+    println!("{}: {:?} per iteration", name, start.elapsed() / ITERATIONS);
+}
+
+fn main() {
+    let mut type_store = ComponentStore::default();
+    let mut archetype_store = ArchetypeComponentStore::default();
+    archetype_store.register_column::<Size>();
+    archetype_store.register_column::<Position>();
+    for id in 0..ENTITIES {
+        type_store.append(Entity(id), components(id));
+        archetype_store.append(Entity(id), components(id));
+    }
+
+    bench("TypeComponentStore::query2", || {
+        type_store
+            .query2::<Size, Position>()
+            .map(|(_, size, position)| size.0.wrapping_add(position.0))
+            .fold(0, u32::wrapping_add)
+    });
+
+    bench("ArchetypeComponentStore::query2", || {
+        archetype_store
+            .query2::<Size, Position>()
+            .map(|(_, size, position)| size.0.wrapping_add(position.0))
+            .fold(0, u32::wrapping_add)
+    });
+}
@@ -9,6 +9,7 @@ struct Size {
 #[derive(Default)]
 struct Name(String);
 
+#[allow(dead_code)]
 #[derive(Default)]
 struct Depth(u32);
 
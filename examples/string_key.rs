@@ -1,11 +1,5 @@
 use dces::prelude::*;
 
-#[derive(Default)]
-struct Name(String);
-
-#[derive(Default)]
-struct Depth(u32);
-
 pub struct PrintSystem;
 impl System<EntityStore, StringComponentStore> for PrintSystem {
     fn run(&self, ecm: &mut EntityComponentManager<EntityStore, StringComponentStore>) {
@@ -73,7 +67,7 @@ fn main() {
                 .build(),
         )
         .build();
-    
+
     world.create_system(PrintSystem).with_priority(1).build();
 
     world.run();
@@ -0,0 +1,196 @@
+#[cfg(feature = "no_std")]
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{component::*, entity::*};
+
+/// A deferred change of the entity component manager.
+pub type Command<E, C> = Box<dyn FnOnce(&mut EntityComponentManager<E, C>)>;
+
+/// The `Commands` queue is used to defer structural changes like removing entities while a
+/// system iterates the stores. The queued commands are applied by `World::run` after each
+/// system run.
+///
+/// Commands are applied in FIFO order, so a command can rely on the changes of all commands
+/// queued before it. Commands queued while applying are applied after the current queue.
+pub struct Commands<E, C>
+where
+    E: EntityStore,
+    C: ComponentStore,
+{
+    queue: Vec<Command<E, C>>,
+}
+
+impl<E, C> Default for Commands<E, C>
+where
+    E: EntityStore,
+    C: ComponentStore,
+{
+    fn default() -> Self {
+        Commands { queue: vec![] }
+    }
+}
+
+impl<E, C> Commands<E, C>
+where
+    E: EntityStore,
+    C: ComponentStore,
+{
+    /// Queues a custom `command`.
+    pub fn push(&mut self, command: impl FnOnce(&mut EntityComponentManager<E, C>) + 'static) {
+        self.queue.push(Box::new(command));
+    }
+
+    /// Queues the creation of a new entity with the given `components`.
+    pub fn create_entity(&mut self, components: C::Components)
+    where
+        C: AppendComponents,
+        C::Components: 'static,
+    {
+        self.push(move |ecm| {
+            ecm.create_entity().components(components).build();
+        });
+    }
+
+    /// Queues adding the given `components` to the `entity`.
+    pub fn add_components(&mut self, entity: impl Into<Entity>, components: C::Components)
+    where
+        C: AppendComponents,
+        C::Components: 'static,
+    {
+        let entity = entity.into();
+        self.push(move |ecm| ecm.component_store_mut().append(entity, components));
+    }
+
+    /// Queues the removal of the given `entity`.
+    pub fn remove_entity(&mut self, entity: impl Into<Entity>) {
+        let entity = entity.into();
+        self.push(move |ecm| ecm.remove_entity(entity));
+    }
+
+    /// Returns the number of queued commands.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns true if no commands are queued.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Removes all queued commands and returns them in queue order.
+    pub fn drain(&mut self) -> Vec<Command<E, C>> {
+        self.queue.drain(..).collect()
+    }
+}
+
+impl<E> Commands<E, TypeComponentStore>
+where
+    E: EntityStore,
+{
+    /// Queues adding the `component` of type `C` to the `entity`.
+    pub fn add_component<C: Component>(&mut self, entity: impl Into<Entity>, component: C) {
+        let entity = entity.into();
+        self.push(move |ecm| ecm.component_store_mut().register(entity, component));
+    }
+
+    /// Queues sharing the component of type `C` of the `source` with the `target`.
+    pub fn add_shared<C: Component>(
+        &mut self,
+        target: impl Into<Entity>,
+        source: impl Into<Entity>,
+    ) {
+        let (target, source) = (target.into(), source.into());
+        self.push(move |ecm| {
+            ecm.component_store_mut()
+                .register_shared::<C>(target, source)
+        });
+    }
+
+    /// Queues the removal of the component of type `C` from the `entity`.
+    pub fn remove_component<C: Component>(&mut self, entity: impl Into<Entity>) {
+        let entity = entity.into();
+        self.push(move |ecm| {
+            ecm.component_store_mut().remove_component::<C>(entity);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue() {
+        let mut commands: Commands<VecEntityStore, TypeComponentStore> = Commands::default();
+        assert!(commands.is_empty());
+
+        commands.remove_entity(Entity(0));
+        commands.create_entity(TypeComponentBuilder::new().build());
+        assert_eq!(commands.len(), 2);
+
+        assert_eq!(commands.drain().len(), 2);
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn apply_commands() {
+        let mut ecm =
+            EntityComponentManager::new(VecEntityStore::default(), TypeComponentStore::default());
+        let entity = ecm
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(1_u32).build())
+            .build();
+
+        ecm.commands().remove_entity(entity);
+        ecm.commands()
+            .create_entity(TypeComponentBuilder::new().with(2_u32).build());
+        assert!(ecm.component_store().contains_entity(entity));
+
+        ecm.apply_commands();
+
+        assert!(!ecm.component_store().contains_entity(entity));
+        assert_eq!(ecm.entity_store().inner, vec![Entity(1)]);
+        assert_eq!(*ecm.component_store().get::<u32>(Entity(1)).unwrap(), 2);
+    }
+
+    #[test]
+    fn component_commands() {
+        let mut ecm =
+            EntityComponentManager::new(VecEntityStore::default(), TypeComponentStore::default());
+        let source = ecm
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(1_u32).build())
+            .build();
+        let target = ecm.create_entity().build();
+
+        ecm.commands().add_component(source, String::from("Source"));
+        ecm.commands().add_shared::<String>(target, source);
+        ecm.commands().remove_component::<u32>(source);
+        ecm.apply_commands();
+
+        let store = ecm.component_store();
+        assert_eq!(store.get::<String>(source).unwrap(), "Source");
+        assert_eq!(store.get::<String>(target).unwrap(), "Source");
+        assert!(store.get::<u32>(source).is_err());
+    }
+
+    #[test]
+    fn fifo_order() {
+        let mut ecm =
+            EntityComponentManager::new(VecEntityStore::default(), TypeComponentStore::default());
+        let entity = ecm.create_entity().build();
+
+        ecm.commands().add_component(entity, 1_u32);
+        ecm.commands().push(move |ecm| {
+            let value = *ecm.component_store().get::<u32>(entity).unwrap();
+            ecm.component_store_mut()
+                .register(entity, format!("Value {}", value));
+        });
+        ecm.commands().add_component(entity, 2_u32);
+        ecm.apply_commands();
+
+        let store = ecm.component_store();
+        assert_eq!(store.get::<String>(entity).unwrap(), "Value 1");
+        assert_eq!(*store.get::<u32>(entity).unwrap(), 2);
+    }
+}
@@ -0,0 +1,814 @@
+use core::{
+    any::{Any, TypeId},
+    fmt, mem,
+};
+
+#[cfg(not(feature = "no_std"))]
+use std::collections::HashMap;
+
+#[cfg(feature = "no_std")]
+use hashbrown::HashMap;
+
+#[cfg(feature = "no_std")]
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use super::{
+    component_store::{downcast_mut, downcast_ref, MAX_SHARE_DEPTH},
+    fx_hasher::FxHashMap,
+    AppendComponents, Component, ComponentStore, Entity, ManageEntities, TypeNameRegistry,
+};
+use crate::error::NotFound;
+
+// Creates an empty column for one component type.
+type NewColumn = fn() -> Box<dyn Column>;
+
+// Stores the components of one type of an archetype. A `Vec<C>` stores them contiguous, the
+// `BoxColumn` is used for types without a registered column.
+trait Column {
+    fn len(&self) -> usize;
+
+    // Pushes the component, it has to be of the type of the column.
+    fn push(&mut self, component: Box<dyn Any>);
+
+    fn swap_remove(&mut self, row: usize) -> Box<dyn Any>;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<C: Component> Column for Vec<C> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn push(&mut self, component: Box<dyn Any>) {
+        let component = component
+            .downcast::<C>()
+            .expect("ArchetypeComponentStore: component does not match its column");
+        Vec::push(self, *component);
+    }
+
+    fn swap_remove(&mut self, row: usize) -> Box<dyn Any> {
+        Box::new(Vec::swap_remove(self, row))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl fmt::Debug for dyn Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Column").field("len", &self.len()).finish()
+    }
+}
+
+#[derive(Default)]
+struct BoxColumn(Vec<Box<dyn Any>>);
+
+impl Column for BoxColumn {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn push(&mut self, component: Box<dyn Any>) {
+        self.0.push(component);
+    }
+
+    fn swap_remove(&mut self, row: usize) -> Box<dyn Any> {
+        self.0.swap_remove(row)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn new_column<C: Component>() -> Box<dyn Column> {
+    Box::new(Vec::<C>::new())
+}
+
+// Returns the components of type `C` of a column. One of both slices is empty.
+fn slices<C: Component>(column: &dyn Column) -> (&[C], &[Box<dyn Any>]) {
+    let column = column.as_any();
+    match column.downcast_ref::<Vec<C>>() {
+        Some(typed) => (typed, &[]),
+        None => match column.downcast_ref::<BoxColumn>() {
+            Some(boxed) => (&[], &boxed.0),
+            None => (&[], &[]),
+        },
+    }
+}
+
+// Returns the mutable components of type `C` of a column. One of both slices is empty.
+fn slices_mut<C: Component>(column: &mut dyn Column) -> (&mut [C], &mut [Box<dyn Any>]) {
+    let column = column.as_any_mut();
+    if column.is::<Vec<C>>() {
+        return (column.downcast_mut::<Vec<C>>().unwrap(), Default::default());
+    }
+
+    match column.downcast_mut::<BoxColumn>() {
+        Some(boxed) => (Default::default(), &mut boxed.0),
+        None => (Default::default(), Default::default()),
+    }
+}
+
+// Holds all entities with the same set of component types. Each column stores the components
+// of one type in the order of `entities`.
+#[derive(Debug)]
+struct Archetype {
+    types: Vec<TypeId>,
+    entities: Vec<Entity>,
+    columns: Vec<Box<dyn Column>>,
+}
+
+impl Archetype {
+    fn new(types: Vec<TypeId>, new_columns: &FxHashMap<TypeId, NewColumn>) -> Self {
+        let columns = types
+            .iter()
+            .map(|type_id| match new_columns.get(type_id) {
+                Some(new_column) => new_column(),
+                None => Box::new(BoxColumn::default()) as Box<dyn Column>,
+            })
+            .collect();
+
+        Archetype {
+            types,
+            entities: vec![],
+            columns,
+        }
+    }
+
+    fn column(&self, type_id: TypeId) -> Option<usize> {
+        self.types.binary_search(&type_id).ok()
+    }
+
+    fn get<C: Component>(&self, row: usize, column: usize) -> Result<&C, NotFound> {
+        match slices::<C>(self.columns[column].as_ref()) {
+            (typed, _) if !typed.is_empty() => Ok(&typed[row]),
+            (_, boxed) => downcast_ref(boxed[row].as_ref()),
+        }
+    }
+
+    fn get_mut<C: Component>(&mut self, row: usize, column: usize) -> Result<&mut C, NotFound> {
+        match slices_mut::<C>(self.columns[column].as_mut()) {
+            (typed, _) if !typed.is_empty() => Ok(&mut typed[row]),
+            (_, boxed) => downcast_mut(boxed[row].as_mut()),
+        }
+    }
+
+    // Returns the components of type `C` in the order of `entities`.
+    fn iter<C: Component>(&self, column: usize) -> impl Iterator<Item = &C> {
+        let (typed, boxed) = slices::<C>(self.columns[column].as_ref());
+        typed
+            .iter()
+            .chain(boxed.iter().filter_map(|c| c.downcast_ref()))
+    }
+}
+
+/// The `ArchetypeComponentStore` stores the components of all entities grouped by archetype,
+/// the exact set of component types of an entity. The components of an archetype are stored
+/// in columns, so queries iterate the matching archetypes row by row instead of looking up
+/// each entity. Adding or removing a component moves the entity to another archetype.
+///
+/// It uses the same components as the `TypeComponentStore`, so entities are built with the
+/// `TypeComponentBuilder`. Components of a type registered with `register` or `register_column`
+/// are stored contiguous, components of other types are boxed.
+#[derive(Default, Debug)]
+pub struct ArchetypeComponentStore {
+    archetypes: Vec<Archetype>,
+    new_columns: FxHashMap<TypeId, NewColumn>,
+    locations: FxHashMap<Entity, (usize, usize)>,
+    shared: FxHashMap<(Entity, TypeId), Entity>,
+}
+
+impl AppendComponents for ArchetypeComponentStore {
+    type Components = (HashMap<TypeId, Box<dyn Any>>, HashMap<TypeId, Entity>);
+
+    fn append(&mut self, entity: Entity, components: Self::Components) {
+        let (components, shared) = components;
+        self.insert(
+            entity,
+            components
+                .into_iter()
+                .filter(|(key, _)| !shared.contains_key(key)),
+        );
+
+        // owned components are replaced by the shared ones
+        if shared
+            .keys()
+            .any(|key| self.position(entity, *key).is_some())
+        {
+            let mut row = self.take_row(entity);
+            row.retain(|(key, _)| !shared.contains_key(key));
+            self.insert_row(entity, row);
+        }
+
+        for (key, value) in shared {
+            self.shared.insert((entity, key), value);
+        }
+    }
+}
+
+impl ComponentStore for ArchetypeComponentStore {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.take_row(entity);
+        self.shared
+            .retain(|k, source| k.0 != entity && *source != entity);
+    }
+
+    fn remove_entities(&mut self, entities: &[Entity]) {
+        let mut entities = entities.to_vec();
+        entities.sort();
+        let removed = |entity: Entity| entities.binary_search(&entity).is_ok();
+
+        for entity in &entities {
+            self.take_row(*entity);
+        }
+
+        self.shared
+            .retain(|k, source| !removed(k.0) && !removed(*source));
+    }
+
+    #[cfg(not(feature = "no_std"))]
+    fn print_entity(&self, entity: Entity) {
+        println!("Components of entity: {}", entity.0);
+        if let Some((archetype, _)) = self.locations.get(&entity) {
+            for type_id in &self.archetypes[*archetype].types {
+                println!("Type: {:?}", type_id);
+            }
+        }
+
+        println!("Shared components of entity: {}", entity.0);
+        for (k, v) in self.shared.iter().filter(|&(k, _)| k.0 == entity) {
+            println!("Type: {:?}, Source: {:?}", k.1, v);
+        }
+    }
+
+    fn describe_entity(&self, entity: Entity, type_names: &TypeNameRegistry) -> String {
+        let name = |type_id: TypeId| match type_names.get(type_id) {
+            Some(name) => name.to_string(),
+            None => format!("{:?}", type_id),
+        };
+
+        let mut owned: Vec<String> = match self.locations.get(&entity) {
+            Some((archetype, _)) => self.archetypes[*archetype]
+                .types
+                .iter()
+                .map(|type_id| format!("    owns {}\n", name(*type_id)))
+                .collect(),
+            None => vec![],
+        };
+        owned.sort();
+
+        let mut shared: Vec<String> = self
+            .shared
+            .iter()
+            .filter(|(k, _)| k.0 == entity)
+            .map(|(k, source)| format!("    shares {} from {:?}\n", name(k.1), source))
+            .collect();
+        shared.sort();
+
+        owned.concat() + &shared.concat()
+    }
+
+    fn check_shared(&self, entity: Entity) -> Result<(), NotFound> {
+        let mut type_ids: Vec<TypeId> = self
+            .shared
+            .keys()
+            .filter(|k| k.0 == entity)
+            .map(|k| k.1)
+            .collect();
+        type_ids.sort();
+
+        for type_id in type_ids {
+            self.source_of(entity, type_id)
+                .map_err(|_| NotFound::Component(type_id))?;
+        }
+
+        Ok(())
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.locations.reserve(additional);
+    }
+
+    fn len(&self) -> usize {
+        self.archetypes
+            .iter()
+            .map(|a| a.entities.len() * a.types.len())
+            .sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+}
+
+impl ManageEntities for ArchetypeComponentStore {
+    fn swap_entities(&mut self, a: Entity, b: Entity) {
+        if a == b {
+            return;
+        }
+
+        let swap = |entity: Entity| match entity {
+            e if e == a => b,
+            e if e == b => a,
+            e => e,
+        };
+
+        let locations = [
+            (b, self.locations.remove(&a)),
+            (a, self.locations.remove(&b)),
+        ];
+        for (entity, location) in locations.iter() {
+            if let Some((archetype, row)) = *location {
+                self.archetypes[archetype].entities[row] = *entity;
+                self.locations.insert(*entity, (archetype, row));
+            }
+        }
+
+        let keys: Vec<(Entity, TypeId)> = self
+            .shared
+            .iter()
+            .filter(|(k, s)| k.0 == a || k.0 == b || **s == a || **s == b)
+            .map(|(k, _)| *k)
+            .collect();
+        let shared: Vec<_> = keys
+            .into_iter()
+            .filter_map(|k| self.shared.remove(&k).map(|s| (k, s)))
+            .collect();
+        for (k, source) in shared {
+            self.shared.insert((swap(k.0), k.1), swap(source));
+        }
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = self
+            .locations
+            .keys()
+            .copied()
+            .chain(self.shared.keys().map(|k| k.0))
+            .collect();
+        entities.sort();
+        entities.dedup();
+        entities
+    }
+
+    fn shared_entities(&self) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = self.shared.keys().map(|k| k.0).collect();
+        entities.sort();
+        entities.dedup();
+        entities
+    }
+
+    fn clear(&mut self) {
+        self.archetypes.clear();
+        self.locations.clear();
+        self.shared.clear();
+    }
+}
+
+impl ArchetypeComponentStore {
+    // Removes the row of the given `entity` from its archetype and returns its components.
+    fn take_row(&mut self, entity: Entity) -> Vec<(TypeId, Box<dyn Any>)> {
+        let (index, row) = match self.locations.remove(&entity) {
+            Some(location) => location,
+            None => return vec![],
+        };
+
+        let archetype = &mut self.archetypes[index];
+        archetype.entities.swap_remove(row);
+        let components = archetype
+            .types
+            .iter()
+            .copied()
+            .zip(archetype.columns.iter_mut().map(|c| c.swap_remove(row)))
+            .collect();
+
+        // the last row was moved to the removed one
+        if let Some(moved) = archetype.entities.get(row) {
+            self.locations.insert(*moved, (index, row));
+        }
+
+        components
+    }
+
+    // Adds a row for the given `entity` to the archetype of its component types.
+    fn insert_row(&mut self, entity: Entity, mut components: Vec<(TypeId, Box<dyn Any>)>) {
+        if components.is_empty() {
+            return;
+        }
+
+        components.sort_by_key(|(type_id, _)| *type_id);
+        let types: Vec<TypeId> = components.iter().map(|(type_id, _)| *type_id).collect();
+
+        let index = match self.archetypes.iter().position(|a| a.types == types) {
+            Some(index) => index,
+            None => {
+                self.archetypes
+                    .push(Archetype::new(types, &self.new_columns));
+                self.archetypes.len() - 1
+            }
+        };
+
+        let archetype = &mut self.archetypes[index];
+        for (column, (_, component)) in archetype.columns.iter_mut().zip(components) {
+            column.push(component);
+        }
+        archetype.entities.push(entity);
+
+        self.locations
+            .insert(entity, (index, archetype.entities.len() - 1));
+    }
+
+    // Moves the given `entity` to the archetype that includes the new `components`. Components
+    // and shared links of the same type are replaced.
+    fn insert(
+        &mut self,
+        entity: Entity,
+        components: impl IntoIterator<Item = (TypeId, Box<dyn Any>)>,
+    ) {
+        let mut row = self.take_row(entity);
+
+        for (type_id, component) in components {
+            self.shared.remove(&(entity, type_id));
+            match row.iter_mut().find(|(t, _)| *t == type_id) {
+                Some(existing) => existing.1 = component,
+                None => row.push((type_id, component)),
+            }
+        }
+
+        self.insert_row(entity, row);
+    }
+
+    // Returns the archetype, row and column of the component with `type_id` the `entity` owns.
+    fn position(&self, entity: Entity, type_id: TypeId) -> Option<(usize, usize, usize)> {
+        let (archetype, row) = *self.locations.get(&entity)?;
+        let column = self.archetypes[archetype].column(type_id)?;
+        Some((archetype, row, column))
+    }
+
+    // Follows the shared links of the component with the given `type_id` to its origin.
+    fn source_of(&self, entity: Entity, type_id: TypeId) -> Result<Entity, NotFound> {
+        let mut source = entity;
+
+        // each shared link is followed at most once, so a cycle ends the search
+        for _ in 0..=self.shared.len().min(MAX_SHARE_DEPTH) {
+            if self.position(source, type_id).is_some() {
+                return Ok(source);
+            }
+
+            source = *self
+                .shared
+                .get(&(source, type_id))
+                .ok_or(NotFound::Entity(entity))?;
+        }
+
+        Err(NotFound::Entity(entity))
+    }
+
+    /// Registers a contiguous column for components of type `C`, so they are not boxed if they
+    /// are added by `append`. Components of type `C` the store already contains are moved to
+    /// contiguous columns.
+    pub fn register_column<C: Component>(&mut self) {
+        let type_id = TypeId::of::<C>();
+        if self.new_columns.insert(type_id, new_column::<C>).is_some() {
+            return;
+        }
+
+        for archetype in &mut self.archetypes {
+            if let Some(column) = archetype.column(type_id) {
+                let mut boxed = mem::replace(&mut archetype.columns[column], new_column::<C>());
+                if let Some(boxed) = boxed.as_any_mut().downcast_mut::<BoxColumn>() {
+                    for component in boxed.0.drain(..) {
+                        archetype.columns[column].push(component);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Register a `component` for the given `entity`. The entity is moved to the archetype that
+    /// includes the type `C`.
+    pub fn register<C: Component>(&mut self, entity: Entity, component: C) {
+        self.register_column::<C>();
+        let component: Box<dyn Any> = Box::new(component);
+        self.insert(entity, Some((TypeId::of::<C>(), component)));
+    }
+
+    /// Registers a sharing of the given component between the given entities.
+    pub fn register_shared<C: Component>(&mut self, target: Entity, source: Entity) {
+        if self.position(target, TypeId::of::<C>()).is_some() {
+            self.remove_component::<C>(target);
+        }
+
+        self.shared.insert((target, TypeId::of::<C>()), source);
+    }
+
+    /// Removes the component of type `C` from the given `entity` and returns it. The entity is
+    /// moved to the archetype without `C`. All shared links that point to the removed component
+    /// are removed too.
+    pub fn remove_component<C: Component>(&mut self, entity: Entity) -> Option<Box<dyn Any>> {
+        let type_id = TypeId::of::<C>();
+        self.shared.remove(&(entity, type_id));
+
+        self.position(entity, type_id)?;
+        let mut row = self.take_row(entity);
+        let index = row.iter().position(|(t, _)| *t == type_id)?;
+        let (_, component) = row.remove(index);
+        self.insert_row(entity, row);
+
+        self.shared
+            .retain(|k, source| !(k.1 == type_id && *source == entity));
+
+        Some(component)
+    }
+
+    /// Returns `true` if the store contains the specific entity.
+    pub fn contains_entity(&self, entity: Entity) -> bool {
+        self.locations.contains_key(&entity)
+    }
+
+    /// Returns `true` if the given `entity` owns or shares a component of type `C`.
+    pub fn contains_component<C: Component>(&self, entity: Entity) -> bool {
+        self.get::<C>(entity).is_ok()
+    }
+
+    /// Returns the number of archetypes.
+    pub fn archetype_count(&self) -> usize {
+        self.archetypes.len()
+    }
+
+    /// Returns a reference of a component of type `C` from the given `entity`. If the entity does
+    /// not exists or it doesn't have a component of type `C` `NotFound` will be returned. If the
+    /// stored component is not of type `C` `NotFound::TypeMismatch` will be returned.
+    pub fn get<C: Component>(&self, entity: Entity) -> Result<&C, NotFound> {
+        let source = self.source_of(entity, TypeId::of::<C>())?;
+        let (archetype, row, column) = self
+            .position(source, TypeId::of::<C>())
+            .ok_or(NotFound::Entity(entity))?;
+
+        self.archetypes[archetype].get(row, column)
+    }
+
+    /// Returns a mutable reference of a component of type `C` from the given `entity`. If the
+    /// entity does not exists or it doesn't have a component of type `C` `NotFound` will be
+    /// returned. If the stored component is not of type `C` `NotFound::TypeMismatch` will be
+    /// returned.
+    pub fn get_mut<C: Component>(&mut self, entity: Entity) -> Result<&mut C, NotFound> {
+        let source = self.source_of(entity, TypeId::of::<C>())?;
+        let (archetype, row, column) = self
+            .position(source, TypeId::of::<C>())
+            .ok_or(NotFound::Entity(entity))?;
+
+        self.archetypes[archetype].get_mut(row, column)
+    }
+
+    /// Returns an iterator over all entities that own a component of type `C` together with a
+    /// reference of the component. Entities that only share the component are skipped.
+    pub fn query<C: Component>(&self) -> impl Iterator<Item = (Entity, &C)> + '_ {
+        self.archetypes
+            .iter()
+            .filter_map(|a| {
+                let column = a.column(TypeId::of::<C>())?;
+                Some(a.entities.iter().copied().zip(a.iter(column)))
+            })
+            .flatten()
+    }
+
+    /// Returns an iterator over all entities that own a component of type `C` together with a
+    /// mutable reference of the component. Entities that only share the component are skipped.
+    pub fn query_mut<C: Component>(&mut self) -> impl Iterator<Item = (Entity, &mut C)> + '_ {
+        self.archetypes
+            .iter_mut()
+            .filter_map(|a| {
+                let column = a.column(TypeId::of::<C>())?;
+                let (typed, boxed) = slices_mut::<C>(a.columns[column].as_mut());
+                let components = typed
+                    .iter_mut()
+                    .chain(boxed.iter_mut().filter_map(|c| c.downcast_mut()));
+                Some(a.entities.iter().copied().zip(components))
+            })
+            .flatten()
+    }
+
+    /// Returns an iterator over all entities that own a component of type `A` and of type `B`
+    /// together with references of both components. Only the archetypes with both types are
+    /// iterated, so shared components are not resolved.
+    pub fn query2<A: Component, B: Component>(
+        &self,
+    ) -> impl Iterator<Item = (Entity, &A, &B)> + '_ {
+        self.archetypes
+            .iter()
+            .filter_map(|a| {
+                let columns = (a.column(TypeId::of::<A>())?, a.column(TypeId::of::<B>())?);
+                Some(
+                    a.entities
+                        .iter()
+                        .copied()
+                        .zip(a.iter::<A>(columns.0).zip(a.iter::<B>(columns.1))),
+                )
+            })
+            .flatten()
+            .map(|(entity, (a, b))| (entity, a, b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::{TypeComponentBuilder, TypeComponentStore};
+
+    #[derive(Debug, PartialEq)]
+    struct Size(u32);
+
+    fn builder_for(id: u32) -> TypeComponentBuilder {
+        let mut builder = TypeComponentBuilder::new().with(Size(id));
+        if id.is_multiple_of(2) {
+            builder = builder.with(id as f64);
+        }
+        if id.is_multiple_of(3) {
+            builder = builder.with(String::from("Button"));
+        }
+        if id.is_multiple_of(7) {
+            builder = builder.with_shared::<Size>(Entity(1));
+        }
+        builder
+    }
+
+    #[test]
+    fn register() {
+        let mut store = ArchetypeComponentStore::default();
+        let entity = Entity::from(1);
+        store.register(entity, Size(5));
+        store.register(entity, String::from("Button"));
+        store.register(entity, Size(7));
+
+        assert_eq!(store.get::<Size>(entity), Ok(&Size(7)));
+        assert_eq!(store.get::<String>(entity), Ok(&String::from("Button")));
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.archetype_count(), 2);
+        assert!(store.contains_entity(entity));
+    }
+
+    #[test]
+    fn migration() {
+        let mut store = ArchetypeComponentStore::default();
+        for id in 0..3 {
+            store.register(Entity(id), Size(id));
+            store.register(Entity(id), id as f64);
+        }
+
+        let removed = store.remove_component::<f64>(Entity(0));
+
+        assert_eq!(*removed.unwrap().downcast::<f64>().unwrap(), 0.0);
+        assert!(!store.contains_component::<f64>(Entity(0)));
+        for id in 0..3 {
+            assert_eq!(store.get::<Size>(Entity(id)), Ok(&Size(id)));
+        }
+        assert_eq!(store.get::<f64>(Entity(2)), Ok(&2.0));
+        assert!(store.remove_component::<f64>(Entity(0)).is_none());
+
+        store.remove_entity(Entity(1));
+        assert!(!store.contains_entity(Entity(1)));
+        assert_eq!(store.get::<f64>(Entity(2)), Ok(&2.0));
+        assert_eq!(store.len(), 3);
+    }
+
+    #[test]
+    fn shared() {
+        let mut store = ArchetypeComponentStore::default();
+        let source = Entity::from(1);
+        let target = Entity::from(2);
+        store.register(source, Size(5));
+        store.register(target, Size(2));
+        store.register_shared::<Size>(target, source);
+
+        *store.get_mut::<Size>(target).unwrap() = Size(6);
+
+        assert_eq!(store.get::<Size>(source), Ok(&Size(6)));
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.shared_entities(), vec![target]);
+        assert!(store.check_shared(target).is_ok());
+
+        store.remove_component::<Size>(source);
+        assert!(store.get::<Size>(target).is_err());
+    }
+
+    #[test]
+    fn swap_entities() {
+        let mut store = ArchetypeComponentStore::default();
+        store.register(Entity(1), Size(1));
+        store.register(Entity(2), Size(2));
+        store.register(Entity(2), 2.0_f64);
+        store.register_shared::<Size>(Entity(3), Entity(1));
+
+        store.swap_entities(Entity(1), Entity(2));
+
+        assert_eq!(store.get::<Size>(Entity(1)), Ok(&Size(2)));
+        assert_eq!(store.get::<f64>(Entity(1)), Ok(&2.0));
+        assert_eq!(store.get::<Size>(Entity(2)), Ok(&Size(1)));
+        assert_eq!(store.get::<Size>(Entity(3)), Ok(&Size(1)));
+    }
+
+    #[test]
+    fn matches_type_component_store() {
+        let mut archetype_store = ArchetypeComponentStore::default();
+        let mut type_store = TypeComponentStore::default();
+        archetype_store.register_column::<Size>();
+
+        for id in 0..20 {
+            archetype_store.append(Entity(id), builder_for(id).build());
+            type_store.append(Entity(id), builder_for(id).build());
+        }
+
+        let shared = || TypeComponentBuilder::new().with_shared::<f64>(Entity(4));
+        archetype_store.append(Entity(2), shared().build());
+        type_store.append(Entity(2), shared().build());
+
+        for id in (0..20).step_by(5) {
+            archetype_store.remove_entity(Entity(id));
+            type_store.remove_entity(Entity(id));
+        }
+
+        for (entity, size) in archetype_store.query_mut::<Size>() {
+            size.0 += entity.0;
+        }
+        for (entity, size) in type_store.query_mut::<Size>() {
+            size.0 += entity.0;
+        }
+
+        assert_eq!(archetype_store.len(), type_store.len());
+        assert_eq!(archetype_store.entities(), type_store.entities());
+        for id in 0..20 {
+            let entity = Entity(id);
+            assert_eq!(archetype_store.get::<Size>(entity), type_store.get(entity));
+            assert_eq!(archetype_store.get::<f64>(entity), type_store.get(entity));
+            assert_eq!(
+                archetype_store.get::<String>(entity),
+                type_store.get(entity)
+            );
+        }
+
+        let mut archetype_query: Vec<(Entity, &Size, &f64)> =
+            archetype_store.query2::<Size, f64>().collect();
+        // the archetype store does not resolve shared components in `query2`
+        let mut type_query: Vec<(Entity, &Size, &f64)> = type_store
+            .query2::<Size, f64>()
+            .filter(|(entity, _, _)| {
+                type_store.source::<Size>(*entity) == Ok(*entity)
+                    && type_store.source::<f64>(*entity) == Ok(*entity)
+            })
+            .collect();
+        archetype_query.sort_by_key(|(entity, _, _)| *entity);
+        type_query.sort_by_key(|(entity, _, _)| *entity);
+        assert_eq!(archetype_query, type_query);
+        assert_eq!(archetype_store.get::<Size>(Entity(14)), Ok(&Size(2)));
+        assert_eq!(archetype_store.get::<f64>(Entity(2)), Ok(&4.0));
+    }
+
+    #[test]
+    fn register_column() {
+        let mut store = ArchetypeComponentStore::default();
+        for id in 0..3 {
+            store.append(
+                Entity(id),
+                TypeComponentBuilder::new().with(Size(id)).build(),
+            );
+        }
+        assert!(store.archetypes[0].columns[0].as_any().is::<BoxColumn>());
+
+        store.register_column::<Size>();
+
+        assert!(store.archetypes[0].columns[0].as_any().is::<Vec<Size>>());
+        for id in 0..3 {
+            assert_eq!(store.get::<Size>(Entity(id)), Ok(&Size(id)));
+        }
+
+        store.register(Entity(3), 3.0_f64);
+        store.register(Entity(3), Size(3));
+        store.remove_entity(Entity(0));
+        assert!(store
+            .archetypes
+            .iter()
+            .flat_map(|a| a.columns.iter())
+            .all(|c| !c.as_any().is::<BoxColumn>()));
+        let mut sizes: Vec<u32> = store.query::<Size>().map(|(_, size)| size.0).collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 2, 3]);
+    }
+}
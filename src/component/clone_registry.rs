@@ -0,0 +1,85 @@
+use core::any::{Any, TypeId};
+
+#[cfg(not(feature = "no_std"))]
+use std::collections::HashMap;
+
+#[cfg(feature = "no_std")]
+use hashbrown::HashMap;
+
+#[cfg(feature = "no_std")]
+use alloc::boxed::Box;
+
+use super::Component;
+use crate::error::NotFound;
+
+type CloneFn = fn(&dyn Any) -> Box<dyn Any>;
+
+/// The `CloneRegistry` stores how to deep copy the component types that are registered in it.
+/// Components are stored as `Box<dyn Any>`, so only registered types could be copied.
+#[derive(Default, Debug)]
+pub struct CloneRegistry {
+    clone_fns: HashMap<TypeId, CloneFn>,
+}
+
+impl CloneRegistry {
+    /// Creates an new registry with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the component type `C`.
+    pub fn register<C: Component + Clone>(&mut self) {
+        self.clone_fns.insert(TypeId::of::<C>(), |component| {
+            Box::new(
+                component
+                    .downcast_ref::<C>()
+                    .expect("CloneRegistry.register: internal downcast error")
+                    .clone(),
+            )
+        });
+    }
+
+    /// Returns `true` if the component type `C` is registered.
+    pub fn contains<C: Component>(&self) -> bool {
+        self.clone_fns.contains_key(&TypeId::of::<C>())
+    }
+
+    /// Deep copies the given `component`. If its type is not registered
+    /// `NotFound::Component` will be returned.
+    pub fn clone_component(&self, component: &dyn Any) -> Result<Box<dyn Any>, NotFound> {
+        let type_id = component.type_id();
+        self.clone_fns
+            .get(&type_id)
+            .map(|clone_fn| clone_fn(component))
+            .ok_or(NotFound::Component(type_id))
+    }
+}
+
+/// This trait is implemented by component stores that could be deep copied with a
+/// `CloneRegistry`.
+pub trait CloneComponentStore: Sized {
+    /// Deep copies the store. Fails if a stored component type is not registered.
+    fn clone_with_registry(&self, registry: &CloneRegistry) -> Result<Self, NotFound>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clone_component() {
+        let mut registry = CloneRegistry::new();
+        registry.register::<String>();
+        assert!(registry.contains::<String>());
+        assert!(!registry.contains::<u32>());
+
+        let component: Box<dyn Any> = Box::new(String::from("Button"));
+        let copy = registry.clone_component(component.as_ref()).unwrap();
+        assert_eq!(copy.downcast_ref::<String>().unwrap(), "Button");
+
+        assert_eq!(
+            registry.clone_component(&5_u32).err(),
+            Some(NotFound::Component(TypeId::of::<u32>()))
+        );
+    }
+}
@@ -1,10 +1,44 @@
 use core::any::{Any, TypeId};
 
-use std::collections::HashMap;
+#[cfg(not(feature = "no_std"))]
+use std::{collections::HashMap, vec};
 
-use super::{Component, ComponentBox, ComponentStore, Entity, SharedComponentBox};
+#[cfg(feature = "no_std")]
+use hashbrown::HashMap;
+
+#[cfg(feature = "no_std")]
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::{self, Vec},
+};
+
+use super::{
+    fx_hasher::FxHashMap, AppendComponents, CloneComponentStore, CloneRegistry, Component,
+    ComponentBox, ComponentStore, DefaultRegistry, Entity, ManageEntities, SharedComponentBox,
+    Tick, TypeNameRegistry,
+};
 use crate::error::NotFound;
 
+/// Maximal number of shared links that are followed to find the entity that owns a component.
+/// Longer chains are treated like a missing component.
+pub const MAX_SHARE_DEPTH: usize = 64;
+
+// Downcasts a stored component. If it is not of type `C` `NotFound::TypeMismatch` is returned.
+pub(super) fn downcast_ref<C: Component>(component: &dyn Any) -> Result<&C, NotFound> {
+    component
+        .downcast_ref()
+        .ok_or(NotFound::TypeMismatch(TypeId::of::<C>()))
+}
+
+// Downcasts a stored component mutable. If it is not of type `C` `NotFound::TypeMismatch` is
+// returned.
+pub(super) fn downcast_mut<C: Component>(component: &mut dyn Any) -> Result<&mut C, NotFound> {
+    component
+        .downcast_mut()
+        .ok_or(NotFound::TypeMismatch(TypeId::of::<C>()))
+}
+
 /// The `TypeComponentBuilder` is used to build a set of type key based components.
 #[derive(Default)]
 pub struct TypeComponentBuilder {
@@ -44,6 +78,13 @@ impl TypeComponentBuilder {
         self
     }
 
+    /// Adds all given component boxes to the entity.
+    pub fn with_many(mut self, components: impl IntoIterator<Item = ComponentBox>) -> Self {
+        self.components
+            .extend(components.into_iter().map(ComponentBox::consume));
+        self
+    }
+
     /// Finishing the creation of the entity.
     pub fn build(self) -> (HashMap<TypeId, Box<dyn Any>>, HashMap<TypeId, Entity>) {
         (self.components, self.shared)
@@ -54,84 +95,340 @@ impl TypeComponentBuilder {
 /// borrow the components of the entities.
 #[derive(Default, Debug)]
 pub struct TypeComponentStore {
-    components: HashMap<(Entity, TypeId), Box<dyn Any>>,
-    shared: HashMap<(Entity, TypeId), Entity>,
+    components: FxHashMap<(Entity, TypeId), Box<dyn Any>>,
+    shared: FxHashMap<(Entity, TypeId), Entity>,
+    changed: FxHashMap<(Entity, TypeId), Tick>,
+    tick: Tick,
 }
 
-impl ComponentStore for TypeComponentStore {
+impl AppendComponents for TypeComponentStore {
     type Components = (HashMap<TypeId, Box<dyn Any>>, HashMap<TypeId, Entity>);
 
     fn append(&mut self, entity: Entity, components: Self::Components) {
         for (key, value) in components.0 {
+            self.shared.remove(&(entity, key));
             self.components.insert((entity, key), value);
+            self.changed.insert((entity, key), self.tick);
         }
         for (key, value) in components.1 {
+            self.components.remove(&(entity, key));
+            self.changed.remove(&(entity, key));
             self.shared.insert((entity, key), value);
         }
     }
+}
 
-    fn remove_entity(&mut self, entity: impl Into<Entity>) {
-        let entity = entity.into();
-        let keys: Vec<(Entity, TypeId)> = self
+impl ComponentStore for TypeComponentStore {
+    // Keys are removed in sorted order so components are dropped deterministically.
+    fn remove_entity(&mut self, entity: Entity) {
+        let mut keys: Vec<(Entity, TypeId)> = self
             .components
             .iter()
             .filter(|&(k, _)| k.0 == entity)
             .map(|(k, _)| *k)
             .collect();
+        keys.sort();
 
         for k in keys {
             self.components.remove(&k);
+            self.changed.remove(&k);
         }
 
-        let keys: Vec<(Entity, TypeId)> = self
-            .shared
-            .iter()
-            .filter(|&(k, _)| k.0 == entity)
-            .map(|(k, _)| *k)
+        self.shared
+            .retain(|k, source| k.0 != entity && *source != entity);
+    }
+
+    fn remove_entities(&mut self, entities: &[Entity]) {
+        let mut entities = entities.to_vec();
+        entities.sort();
+        let removed = |entity: Entity| entities.binary_search(&entity).is_ok();
+
+        let mut keys: Vec<(Entity, TypeId)> = self
+            .components
+            .keys()
+            .filter(|k| removed(k.0))
+            .copied()
             .collect();
+        keys.sort();
 
         for k in keys {
-            self.shared.remove(&k);
+            self.components.remove(&k);
+            self.changed.remove(&k);
         }
+
+        self.shared
+            .retain(|k, source| !removed(k.0) && !removed(*source));
     }
 
-    fn print_entity(&self, entity: impl Into<Entity>) {
-        let entity = entity.into();
-        let _blub = self
+    #[cfg(not(feature = "no_std"))]
+    fn print_entity(&self, entity: Entity) {
+        println!("{:?}", entity);
+        print!(
+            "{}",
+            self.describe_entity(entity, &TypeNameRegistry::default())
+        );
+    }
+
+    fn describe_entity(&self, entity: Entity, type_names: &TypeNameRegistry) -> String {
+        let name = |type_id: TypeId| match type_names.get(type_id) {
+            Some(name) => name.to_string(),
+            None => format!("{:?}", type_id),
+        };
+
+        let mut owned: Vec<String> = self
             .components
+            .keys()
+            .filter(|k| k.0 == entity)
+            .map(|k| format!("    owns {}\n", name(k.1)))
+            .collect();
+        owned.sort();
+
+        let mut shared: Vec<String> = self
+            .shared
             .iter()
             .filter(|(k, _)| k.0 == entity)
-            .map(|(_, _)| println!("blub"));
+            .map(|(k, source)| format!("    shares {} from {:?}\n", name(k.1), source))
+            .collect();
+        shared.sort();
+
+        owned.concat() + &shared.concat()
+    }
+
+    fn check_shared(&self, entity: Entity) -> Result<(), NotFound> {
+        let mut type_ids: Vec<TypeId> = self
+            .shared
+            .keys()
+            .filter(|k| k.0 == entity)
+            .map(|k| k.1)
+            .collect();
+        type_ids.sort();
+
+        for type_id in type_ids {
+            self.source_of(entity, type_id)
+                .map_err(|_| NotFound::Component(type_id))?;
+        }
+
+        Ok(())
+    }
+
+    fn set_tick(&mut self, tick: Tick) {
+        self.tick = tick;
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.components.reserve(additional);
+    }
+
+    fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+}
+
+impl ManageEntities for TypeComponentStore {
+    fn swap_entities(&mut self, a: Entity, b: Entity) {
+        if a == b {
+            return;
+        }
+
+        let swap = |entity: Entity| match entity {
+            e if e == a => b,
+            e if e == b => a,
+            e => e,
+        };
+
+        let keys: Vec<(Entity, TypeId)> = self
+            .components
+            .keys()
+            .filter(|k| k.0 == a || k.0 == b)
+            .copied()
+            .collect();
+        let components: Vec<_> = keys
+            .into_iter()
+            .filter_map(|k| {
+                let changed = self.changed.remove(&k);
+                self.components.remove(&k).map(|c| (k, c, changed))
+            })
+            .collect();
+        for (k, component, changed) in components {
+            self.components.insert((swap(k.0), k.1), component);
+            if let Some(changed) = changed {
+                self.changed.insert((swap(k.0), k.1), changed);
+            }
+        }
+
+        let keys: Vec<(Entity, TypeId)> = self
+            .shared
+            .iter()
+            .filter(|(k, s)| k.0 == a || k.0 == b || **s == a || **s == b)
+            .map(|(k, _)| *k)
+            .collect();
+        let shared: Vec<_> = keys
+            .into_iter()
+            .filter_map(|k| self.shared.remove(&k).map(|s| (k, s)))
+            .collect();
+        for (k, source) in shared {
+            self.shared.insert((swap(k.0), k.1), swap(source));
+        }
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = self
+            .components
+            .keys()
+            .chain(self.shared.keys())
+            .map(|k| k.0)
+            .collect();
+        entities.sort();
+        entities.dedup();
+        entities
+    }
+
+    fn shared_entities(&self) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = self.shared.keys().map(|k| k.0).collect();
+        entities.sort();
+        entities.dedup();
+        entities
+    }
+
+    fn clear(&mut self) {
+        self.components.clear();
+        self.shared.clear();
+        self.changed.clear();
+    }
+}
+
+impl CloneComponentStore for TypeComponentStore {
+    fn clone_with_registry(&self, registry: &CloneRegistry) -> Result<Self, NotFound> {
+        let mut components = FxHashMap::default();
+        components.reserve(self.components.len());
+        for (key, component) in &self.components {
+            components.insert(*key, registry.clone_component(component.as_ref())?);
+        }
+
+        Ok(TypeComponentStore {
+            components,
+            shared: self.shared.clone(),
+            changed: self.changed.clone(),
+            tick: self.tick,
+        })
     }
 }
 
 impl TypeComponentStore {
-    /// Register a `component` for the given `entity`.
+    /// Register a `component` for the given `entity`. A shared link of the entity to a component
+    /// of type `C` is replaced.
     pub fn register<C: Component>(&mut self, entity: Entity, component: C) {
+        self.shared.remove(&(entity, TypeId::of::<C>()));
         self.components
             .insert((entity, TypeId::of::<C>()), Box::new(component));
+        self.changed.insert((entity, TypeId::of::<C>()), self.tick);
+    }
+
+    /// Registers the `component` for the given `entity` and returns the component of type `C`
+    /// the entity owned before, if any.
+    pub fn replace_component<C: Component>(&mut self, entity: Entity, component: C) -> Option<C> {
+        self.shared.remove(&(entity, TypeId::of::<C>()));
+        self.changed.insert((entity, TypeId::of::<C>()), self.tick);
+        self.components
+            .insert((entity, TypeId::of::<C>()), Box::new(component))
+            .and_then(|old| old.downcast::<C>().ok().map(|old| *old))
     }
 
     /// Registers a sharing of the given component between the given entities.
     pub fn register_shared<C: Component>(&mut self, target: Entity, source: Entity) {
         let target_key = (target, TypeId::of::<C>());
         self.components.remove(&target_key);
+        self.changed.remove(&target_key);
         self.shared.insert(target_key, source);
     }
 
+    /// Registers a sharing of the component of type `C` like `register_shared`, but keeps a
+    /// component the `target` owns. If the target owns a component of type `C`
+    /// `NotFound::OwnedComponent` will be returned and nothing is changed.
+    pub fn register_shared_keep_local<C: Component>(
+        &mut self,
+        target: Entity,
+        source: Entity,
+    ) -> Result<(), NotFound> {
+        if self.components.contains_key(&(target, TypeId::of::<C>())) {
+            return Err(NotFound::OwnedComponent(target));
+        }
+
+        self.shared.insert((target, TypeId::of::<C>()), source);
+        Ok(())
+    }
+
     /// Registers a sharing of the given component between the given entities.
     pub fn register_shared_box(&mut self, target: impl Into<Entity>, source: SharedComponentBox) {
         let target_key = (target.into(), source.type_id);
         self.components.remove(&target_key);
+        self.changed.remove(&target_key);
         self.shared.insert(target_key, source.source);
     }
 
-    /// Register a `component_box` for the given `entity`.
+    /// Register a `component_box` for the given `entity`. A shared link of the entity to a
+    /// component of the same type is replaced.
     pub fn register_box(&mut self, entity: impl Into<Entity>, component_box: ComponentBox) {
         let entity = entity.into();
         let (type_id, component) = component_box.consume();
+        self.shared.remove(&(entity, type_id));
 
         self.components.insert((entity, type_id), component);
+        self.changed.insert((entity, type_id), self.tick);
+    }
+
+    /// Removes the given `entity` like `remove_entity`, but returns its owned components with
+    /// their type ids instead of dropping them. The components are sorted by type id.
+    pub fn remove_entity_drain(&mut self, entity: Entity) -> Vec<(TypeId, Box<dyn Any>)> {
+        let mut keys: Vec<(Entity, TypeId)> = self
+            .components
+            .keys()
+            .filter(|k| k.0 == entity)
+            .copied()
+            .collect();
+        keys.sort();
+
+        let components = keys
+            .into_iter()
+            .filter_map(|k| {
+                self.changed.remove(&k);
+                self.components.remove(&k).map(|c| (k.1, c))
+            })
+            .collect();
+
+        self.shared
+            .retain(|k, source| k.0 != entity && *source != entity);
+
+        components
+    }
+
+    /// Removes the component of type `C` from the given `entity` and returns it. All shared
+    /// links that point to the removed component are removed too.
+    pub fn remove_component<C: Component>(&mut self, entity: Entity) -> Option<Box<dyn Any>> {
+        let type_id = TypeId::of::<C>();
+        self.shared.remove(&(entity, type_id));
+
+        let component = self.components.remove(&(entity, type_id));
+        self.changed.remove(&(entity, type_id));
+
+        if component.is_some() {
+            let keys: Vec<(Entity, TypeId)> = self
+                .shared
+                .iter()
+                .filter(|&(k, s)| k.1 == type_id && *s == entity)
+                .map(|(k, _)| *k)
+                .collect();
+
+            for k in keys {
+                self.shared.remove(&k);
+            }
+        }
+
+        component
     }
 
     /// Returns the number of components in the store.
@@ -154,25 +451,56 @@ impl TypeComponentStore {
         self.components.contains_key(&(entity, TypeId::of::<C>()))
     }
 
-    // Search the the source in the entity map.
-    fn source_from_shared<C: Component>(&self, entity: Entity) -> Result<Entity, NotFound> {
-        self.shared
-            .get(&(entity, TypeId::of::<C>()))
-            .ok_or_else(|| NotFound::Entity(entity))
-            .map(|s| *s)
+    /// Returns the entity that owns the component of type `C` of the given `entity`. Chains of
+    /// shared components are followed to their origin, up to `MAX_SHARE_DEPTH` links. If no
+    /// entity owns the component or the chain is longer `NotFound::Entity` will be returned.
+    pub fn source<C: Component>(&self, entity: Entity) -> Result<Entity, NotFound> {
+        self.source_of(entity, TypeId::of::<C>())
     }
 
-    // Returns the source. First search in entities map. If not found search in shared entity map.
-    fn source<C: Component>(&self, entity: Entity) -> Result<Entity, NotFound> {
-        if !self.components.contains_key(&(entity, TypeId::of::<C>())) {
-            return self.source_from_shared::<C>(entity);
+    // Follows the shared links of the component with the given `type_id` to its origin.
+    fn source_of(&self, entity: Entity, type_id: TypeId) -> Result<Entity, NotFound> {
+        let mut source = entity;
+
+        // each shared link is followed at most once, so a cycle ends the search
+        for _ in 0..=self.shared.len().min(MAX_SHARE_DEPTH) {
+            if self.components.contains_key(&(source, type_id)) {
+                return Ok(source);
+            }
+
+            source = *self
+                .shared
+                .get(&(source, type_id))
+                .ok_or(NotFound::Entity(entity))?;
         }
 
-        Result::Ok(entity)
+        Err(NotFound::Entity(entity))
+    }
+
+    /// Returns the entity that owns the component of type `C` of the given `entity` together with
+    /// all entities that share it, also through chains, sorted by id. If the entity has no
+    /// component of type `C` an empty vector will be returned.
+    pub fn entities_of_component<C: Component>(&self, entity: Entity) -> Vec<Entity> {
+        let source = match self.source::<C>(entity) {
+            Ok(source) => source,
+            Err(_) => return vec![],
+        };
+
+        let mut entities: Vec<Entity> = self
+            .shared
+            .keys()
+            .filter(|k| k.1 == TypeId::of::<C>())
+            .map(|k| k.0)
+            .filter(|e| self.source::<C>(*e) == Ok(source))
+            .collect();
+        entities.push(source);
+        entities.sort();
+        entities
     }
 
     /// Returns a reference of a component of type `C` from the given `entity`. If the entity does
-    /// not exists or it doesn't have a component of type `C` `NotFound` will be returned.
+    /// not exists or it doesn't have a component of type `C` `NotFound` will be returned. If the
+    /// stored component is not of type `C` `NotFound::TypeMismatch` will be returned.
     pub fn get<C: Component>(&self, entity: Entity) -> Result<&C, NotFound> {
         let source = self.source::<C>(entity);
 
@@ -180,34 +508,322 @@ impl TypeComponentStore {
             Ok(entity) => self
                 .components
                 .get(&(entity, TypeId::of::<C>()))
-                .ok_or_else(|| NotFound::Entity(entity))
-                .map(|component| {
-                    component
-                        .downcast_ref()
-                        .expect("EntityComponentManager.get: internal downcast error")
-                }),
+                .ok_or(NotFound::Entity(entity))
+                .and_then(|component| downcast_ref(component.as_ref())),
             Err(_) => Result::Err(NotFound::Entity(entity)),
         }
     }
 
+    /// Returns a reference of a component of type `C` from the given `entity`. If the entity
+    /// has no component of type `C`, also if its shared source is missing, the default of the
+    /// `registry` is registered for it first and replaces the shared link. If no default is
+    /// registered `NotFound::Component` will be returned.
+    pub fn get_or_default<C: Component>(
+        &mut self,
+        entity: Entity,
+        registry: &DefaultRegistry,
+    ) -> Result<&C, NotFound> {
+        let type_id = TypeId::of::<C>();
+        if self.source_of(entity, type_id).is_err() {
+            let component = registry.default_component(type_id)?;
+            self.shared.remove(&(entity, type_id));
+            self.components.insert((entity, type_id), component);
+            self.changed.insert((entity, type_id), self.tick);
+        }
+
+        self.get::<C>(entity)
+    }
+
+    /// Returns `true` if the given `entity` owns or shares a component of type `C`.
+    pub fn contains_component<C: Component>(&self, entity: Entity) -> bool {
+        self.get_opt::<C>(entity).is_some()
+    }
+
+    /// Returns a reference of a component of type `C` from the given `entity` or `None` if the
+    /// entity doesn't have a component of type `C`.
+    pub fn get_opt<C: Component>(&self, entity: Entity) -> Option<&C> {
+        self.source::<C>(entity)
+            .ok()
+            .and_then(|source| self.components.get(&(source, TypeId::of::<C>())))
+            .and_then(|component| component.downcast_ref())
+    }
+
+    /// Returns an iterator over all entities that own a component of type `C` together with a
+    /// reference of the component. Entities that only share the component are skipped.
+    pub fn query<C: Component>(&self) -> impl Iterator<Item = (Entity, &C)> + '_ {
+        self.components
+            .iter()
+            .filter(|(k, _)| k.1 == TypeId::of::<C>())
+            .filter_map(|(k, component)| component.downcast_ref().map(|c| (k.0, c)))
+    }
+
+    /// Returns an iterator over all entities that own a component of type `C` together with a
+    /// mutable reference of the component. Entities that only share the component are skipped.
+    /// All iterated components are marked as changed.
+    pub fn query_mut<C: Component>(&mut self) -> impl Iterator<Item = (Entity, &mut C)> + '_ {
+        for key in self.components.keys().filter(|k| k.1 == TypeId::of::<C>()) {
+            self.changed.insert(*key, self.tick);
+        }
+
+        self.components
+            .iter_mut()
+            .filter(|(k, _)| k.1 == TypeId::of::<C>())
+            .filter_map(|(k, component)| component.downcast_mut().map(|c| (k.0, c)))
+    }
+
+    /// Returns an iterator over all entities with a component of type `C` that was registered or
+    /// mutably borrowed at tick `since` or later. Shared components are resolved to their
+    /// source.
+    pub fn query_changed<C: Component>(
+        &self,
+        since: Tick,
+    ) -> impl Iterator<Item = (Entity, &C)> + '_ {
+        self.components
+            .keys()
+            .chain(self.shared.keys())
+            .filter(|k| k.1 == TypeId::of::<C>())
+            .filter(move |k| {
+                self.source::<C>(k.0)
+                    .ok()
+                    .and_then(|source| self.changed.get(&(source, k.1)))
+                    .is_some_and(|tick| *tick >= since)
+            })
+            .filter_map(move |k| self.get::<C>(k.0).ok().map(|c| (k.0, c)))
+    }
+
+    /// Returns the tick that marks changed components.
+    pub fn tick(&self) -> Tick {
+        self.tick
+    }
+
+    // Marks the component with the given `key` as changed in the current tick.
+    fn mark_changed(&mut self, key: (Entity, TypeId)) {
+        if self.components.contains_key(&key) {
+            self.changed.insert(key, self.tick);
+        }
+    }
+
+    /// Returns an iterator over all entities that have a component of type `A` and of type `B`
+    /// together with references of both components. Shared components are resolved to their
+    /// source. The store is scanned once for the entities with a component of type `A`.
+    pub fn query2<A: Component, B: Component>(
+        &self,
+    ) -> impl Iterator<Item = (Entity, &A, &B)> + '_ {
+        self.components
+            .keys()
+            .chain(self.shared.keys())
+            .filter(|k| k.1 == TypeId::of::<A>())
+            .filter_map(move |k| match (self.get::<A>(k.0), self.get::<B>(k.0)) {
+                (Ok(a), Ok(b)) => Some((k.0, a, b)),
+                _ => None,
+            })
+    }
+
     /// Returns a mutable reference of a component of type `C` from the given `entity`. If the entity does
-    /// not exists or it doesn't have a component of type `C` `NotFound` will be returned.
+    /// not exists or it doesn't have a component of type `C` `NotFound` will be returned. If the
+    /// stored component is not of type `C` `NotFound::TypeMismatch` will be returned. The
+    /// component is marked as changed.
     pub fn get_mut<C: Component>(&mut self, entity: Entity) -> Result<&mut C, NotFound> {
         let source = self.source::<C>(entity);
+        if let Ok(source) = source {
+            self.mark_changed((source, TypeId::of::<C>()));
+        }
 
         match source {
             Ok(entity) => self
                 .components
                 .get_mut(&(entity, TypeId::of::<C>()))
-                .ok_or_else(|| NotFound::Entity(entity))
-                .map(|component| {
-                    component
-                        .downcast_mut()
-                        .expect("EntityComponentManager.get_mut: internal downcast error")
-                }),
+                .ok_or(NotFound::Entity(entity))
+                .and_then(|component| downcast_mut(component.as_mut())),
             Err(_) => Result::Err(NotFound::Entity(entity)),
         }
     }
+
+    /// Returns mutable references of the components of type `A` and `B` from the given `entity`.
+    /// If `A` and `B` are the same type `NotFound::Aliased` will be returned.
+    pub fn get2_mut<A: Component, B: Component>(
+        &mut self,
+        entity: Entity,
+    ) -> Result<(&mut A, &mut B), NotFound> {
+        if TypeId::of::<A>() == TypeId::of::<B>() {
+            return Err(NotFound::Aliased(TypeId::of::<A>()));
+        }
+
+        let a = (self.source::<A>(entity)?, TypeId::of::<A>());
+        let b = (self.source::<B>(entity)?, TypeId::of::<B>());
+
+        self.mark_changed(a);
+        self.mark_changed(b);
+
+        match self.components.get_disjoint_mut([&a, &b]) {
+            [Some(a), Some(b)] => Ok((downcast_mut(a.as_mut())?, downcast_mut(b.as_mut())?)),
+            _ => Err(NotFound::Entity(entity)),
+        }
+    }
+
+    /// Returns mutable references of the components of type `C` from the entities `a` and `b`.
+    /// If both entities are the same or share the same component `NotFound::Aliased` will be
+    /// returned.
+    pub fn get_pair_mut<C: Component>(
+        &mut self,
+        a: Entity,
+        b: Entity,
+    ) -> Result<(&mut C, &mut C), NotFound> {
+        let a_key = (self.source::<C>(a)?, TypeId::of::<C>());
+        let b_key = (self.source::<C>(b)?, TypeId::of::<C>());
+
+        if a_key == b_key {
+            return Err(NotFound::Aliased(TypeId::of::<C>()));
+        }
+
+        self.mark_changed(a_key);
+        self.mark_changed(b_key);
+
+        match self.components.get_disjoint_mut([&a_key, &b_key]) {
+            [Some(a), Some(b)] => Ok((downcast_mut(a.as_mut())?, downcast_mut(b.as_mut())?)),
+            [None, _] => Err(NotFound::Entity(a)),
+            [_, None] => Err(NotFound::Entity(b)),
+        }
+    }
+
+    /// Returns mutable references of the components of type `A`, `B` and `C` from the given
+    /// `entity`. If a type is requested twice `NotFound::Aliased` will be returned.
+    pub fn get3_mut<A: Component, B: Component, C: Component>(
+        &mut self,
+        entity: Entity,
+    ) -> Result<(&mut A, &mut B, &mut C), NotFound> {
+        let (a, b, c) = (TypeId::of::<A>(), TypeId::of::<B>(), TypeId::of::<C>());
+        if a == b || a == c {
+            return Err(NotFound::Aliased(a));
+        }
+        if b == c {
+            return Err(NotFound::Aliased(b));
+        }
+
+        let a = (self.source::<A>(entity)?, a);
+        let b = (self.source::<B>(entity)?, b);
+        let c = (self.source::<C>(entity)?, c);
+
+        self.mark_changed(a);
+        self.mark_changed(b);
+        self.mark_changed(c);
+
+        match self.components.get_disjoint_mut([&a, &b, &c]) {
+            [Some(a), Some(b), Some(c)] => Ok((
+                downcast_mut(a.as_mut())?,
+                downcast_mut(b.as_mut())?,
+                downcast_mut(c.as_mut())?,
+            )),
+            _ => Err(NotFound::Entity(entity)),
+        }
+    }
+
+    /// Splits the store into a mutable view of all components of type `A` and a read-only view
+    /// of all components of type `B`. The components of type `A` are marked as changed. If `A`
+    /// and `B` are the same type `NotFound::Aliased` will be returned.
+    pub fn split_mut<A: Component, B: Component>(
+        &mut self,
+    ) -> Result<(QueryMut<'_, A>, Query<'_, B>), NotFound> {
+        if TypeId::of::<A>() == TypeId::of::<B>() {
+            return Err(NotFound::Aliased(TypeId::of::<A>()));
+        }
+
+        for key in self.components.keys().filter(|k| k.1 == TypeId::of::<A>()) {
+            self.changed.insert(*key, self.tick);
+        }
+
+        let mut a = Vec::new();
+        let mut b = FxHashMap::default();
+
+        for (k, component) in self.components.iter_mut() {
+            if k.1 == TypeId::of::<A>() {
+                if let Some(component) = component.downcast_mut() {
+                    a.push((k.0, component));
+                }
+            } else if k.1 == TypeId::of::<B>() {
+                let component: &Box<dyn Any> = component;
+                if let Some(component) = component.downcast_ref() {
+                    b.insert(k.0, component);
+                }
+            }
+        }
+
+        a.sort_by_key(|(entity, _)| *entity);
+
+        Ok((
+            QueryMut { components: a },
+            Query {
+                components: b,
+                shared: &self.shared,
+            },
+        ))
+    }
+}
+
+/// Mutable view of all owned components of type `C` of a `TypeComponentStore`. It is created
+/// by `TypeComponentStore::split_mut`.
+pub struct QueryMut<'a, C: Component> {
+    components: Vec<(Entity, &'a mut C)>,
+}
+
+impl<'a, C: Component> QueryMut<'a, C> {
+    /// Returns the number of components of the view.
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Returns `true` if the view contains no components.
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+}
+
+impl<'a, C: Component> IntoIterator for QueryMut<'a, C> {
+    type Item = (Entity, &'a mut C);
+    type IntoIter = vec::IntoIter<(Entity, &'a mut C)>;
+
+    /// Iterates over all entities of the view together with a mutable reference of the
+    /// component, sorted by entity.
+    fn into_iter(self) -> Self::IntoIter {
+        self.components.into_iter()
+    }
+}
+
+/// Read-only view of all components of type `C` of a `TypeComponentStore`. It is created by
+/// `TypeComponentStore::split_mut`.
+pub struct Query<'a, C: Component> {
+    components: FxHashMap<Entity, &'a C>,
+    shared: &'a FxHashMap<(Entity, TypeId), Entity>,
+}
+
+impl<'a, C: Component> Query<'a, C> {
+    /// Returns a reference of the component of the given `entity`. Shared components are
+    /// resolved to their source. If the entity has no component of type `C` `NotFound` will be
+    /// returned.
+    pub fn get(&self, entity: Entity) -> Result<&'a C, NotFound> {
+        let mut source = entity;
+
+        for _ in 0..=self.shared.len().min(MAX_SHARE_DEPTH) {
+            if let Some(component) = self.components.get(&source) {
+                return Ok(component);
+            }
+
+            source = *self
+                .shared
+                .get(&(source, TypeId::of::<C>()))
+                .ok_or(NotFound::Entity(entity))?;
+        }
+
+        Err(NotFound::Entity(entity))
+    }
+
+    /// Returns an iterator over all entities that own a component of the view together with a
+    /// reference of the component.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &'a C)> + '_ {
+        self.components
+            .iter()
+            .map(|(entity, component)| (*entity, *component))
+    }
 }
 
 #[cfg(test)]
@@ -264,6 +880,143 @@ mod tests {
         assert!(!store.contains_entity(entity));
     }
 
+    #[test]
+    fn remove_entity_removes_all_components() {
+        let mut store = TypeComponentStore::default();
+        let entity = Entity::from(1);
+        let other = Entity::from(2);
+        store.register(entity, String::from("Test"));
+        store.register(entity, 5_f64);
+        store.register(other, 3_u32);
+        store.register_shared::<u32>(entity, other);
+        assert_eq!(store.len(), 3);
+
+        store.remove_entity(entity);
+
+        assert!(!store.contains_entity(entity));
+        assert_eq!(store.len(), 1);
+        assert!(store.get::<u32>(entity).is_err());
+        assert_eq!(store.get::<u32>(other), Ok(&3));
+    }
+
+    #[test]
+    fn remove_component() {
+        let mut store = TypeComponentStore::default();
+        let entity = Entity::from(1);
+        store.register(entity, String::from("Test"));
+        store.register(entity, 5_f64);
+
+        let component = store.remove_component::<String>(entity);
+
+        assert_eq!(
+            *component.unwrap().downcast::<String>().unwrap(),
+            String::from("Test")
+        );
+        assert_eq!(store.get::<String>(entity), Err(NotFound::Entity(entity)));
+        assert!(store.get::<f64>(entity).is_ok());
+        assert!(store.remove_component::<String>(entity).is_none());
+    }
+
+    #[test]
+    fn remove_shared_component() {
+        let mut store = TypeComponentStore::default();
+        let source = Entity::from(1);
+        let target = Entity::from(2);
+        store.register(source, String::from("Test"));
+        store.register_shared::<String>(target, source);
+
+        assert!(store.remove_component::<String>(source).is_some());
+        assert!(store.get::<String>(source).is_err());
+        assert!(store.get::<String>(target).is_err());
+        assert!(store.shared.is_empty());
+    }
+
+    #[test]
+    fn contains_component() {
+        let mut store = TypeComponentStore::default();
+        let source = Entity::from(1);
+        let target = Entity::from(2);
+        store.register(source, String::from("Test"));
+        store.register_shared::<String>(target, source);
+
+        assert!(store.contains_component::<String>(source));
+        assert!(store.contains_component::<String>(target));
+        assert!(!store.contains_component::<u32>(source));
+        assert!(!store.contains_component::<String>(Entity::from(3)));
+    }
+
+    #[test]
+    fn get_opt() {
+        let mut store = TypeComponentStore::default();
+        let source = Entity::from(1);
+        let target = Entity::from(2);
+        store.register(source, String::from("Test"));
+        store.register_shared::<String>(target, source);
+
+        assert_eq!(store.get_opt::<String>(source), Some(&String::from("Test")));
+        assert_eq!(store.get_opt::<String>(target), Some(&String::from("Test")));
+        assert_eq!(store.get_opt::<u32>(source), None);
+        assert_eq!(store.get_opt::<String>(Entity::from(3)), None);
+    }
+
+    #[test]
+    fn query() {
+        let mut store = TypeComponentStore::default();
+        store.register(Entity::from(1), String::from("One"));
+        store.register(Entity::from(2), 5_f64);
+        store.register(Entity::from(3), String::from("Three"));
+        store.register_shared::<String>(Entity::from(4), Entity::from(1));
+
+        let mut result: Vec<(Entity, &String)> = store.query::<String>().collect();
+        result.sort_by_key(|(e, _)| *e);
+
+        assert_eq!(
+            result,
+            vec![
+                (Entity::from(1), &String::from("One")),
+                (Entity::from(3), &String::from("Three"))
+            ]
+        );
+    }
+
+    #[test]
+    fn query_mut() {
+        let mut store = TypeComponentStore::default();
+        store.register(Entity::from(1), 1_u32);
+        store.register(Entity::from(2), String::from("Two"));
+        store.register(Entity::from(3), 3_u32);
+
+        for (_, value) in store.query_mut::<u32>() {
+            *value += 1;
+        }
+
+        assert_eq!(store.query_mut::<u32>().count(), 2);
+        assert_eq!(*store.get::<u32>(Entity::from(1)).unwrap(), 2);
+        assert_eq!(*store.get::<u32>(Entity::from(3)).unwrap(), 4);
+    }
+
+    #[test]
+    fn query2() {
+        let mut store = TypeComponentStore::default();
+        store.register(Entity::from(1), String::from("One"));
+        store.register(Entity::from(1), 1_u32);
+        store.register(Entity::from(2), String::from("Two"));
+        store.register(Entity::from(3), 3_u32);
+        store.register(Entity::from(4), 4_u32);
+        store.register_shared::<String>(Entity::from(4), Entity::from(2));
+
+        let mut result: Vec<(Entity, &String, &u32)> = store.query2::<String, u32>().collect();
+        result.sort_by_key(|(e, _, _)| *e);
+
+        assert_eq!(
+            result,
+            vec![
+                (Entity::from(1), &String::from("One"), &1),
+                (Entity::from(4), &String::from("Two"), &4)
+            ]
+        );
+    }
+
     #[test]
     fn register() {
         let mut store = TypeComponentStore::default();
@@ -275,13 +1028,22 @@ mod tests {
         assert!(store.get::<String>(entity).is_ok());
     }
 
+    #[test]
+    fn reserve() {
+        let mut store = TypeComponentStore::default();
+        store.reserve(100);
+
+        assert!(store.components.capacity() >= 100);
+        assert!(store.is_empty());
+    }
+
     #[test]
     fn len() {
         let mut store = TypeComponentStore::default();
         let entity = Entity::from(1);
 
         store.register(entity, String::from("Test"));
-        store.register(entity, 5 as f64);
+        store.register(entity, 5_f64);
 
         assert_eq!(store.len(), 2);
     }
@@ -330,4 +1092,419 @@ mod tests {
         assert!(store.is_origin::<String>(entity));
         assert!(!store.is_origin::<String>(target));
     }
+
+    #[test]
+    fn get2_mut() {
+        let mut store = TypeComponentStore::default();
+        let entity = Entity::from(1);
+        store.register(entity, 5_u32);
+        store.register(entity, String::from("Button"));
+
+        let (counter, name) = store.get2_mut::<u32, String>(entity).unwrap();
+        *counter += 1;
+        name.push('s');
+
+        assert_eq!(*store.get::<u32>(entity).unwrap(), 6);
+        assert_eq!(store.get::<String>(entity).unwrap(), "Buttons");
+        assert!(store.get2_mut::<u32, f64>(entity).is_err());
+    }
+
+    #[test]
+    fn get2_mut_same_type() {
+        let mut store = TypeComponentStore::default();
+        let entity = Entity::from(1);
+        store.register(entity, 5_u32);
+
+        assert_eq!(
+            store.get2_mut::<u32, u32>(entity).err(),
+            Some(NotFound::Aliased(TypeId::of::<u32>()))
+        );
+    }
+
+    #[test]
+    fn get3_mut() {
+        let mut store = TypeComponentStore::default();
+        let entity = Entity::from(1);
+        let source = Entity::from(2);
+        store.register(entity, 5_u32);
+        store.register(entity, String::from("Button"));
+        store.register(source, 1.5_f64);
+        store.register_shared::<f64>(entity, source);
+
+        let (counter, _, scale) = store.get3_mut::<u32, String, f64>(entity).unwrap();
+        *counter += 1;
+        *scale *= 2.0;
+
+        assert_eq!(*store.get::<u32>(entity).unwrap(), 6);
+        assert_eq!(*store.get::<f64>(source).unwrap(), 3.0);
+        assert_eq!(
+            store.get3_mut::<u32, String, u32>(entity).err(),
+            Some(NotFound::Aliased(TypeId::of::<u32>()))
+        );
+        assert_eq!(
+            store.get3_mut::<u32, String, String>(entity).err(),
+            Some(NotFound::Aliased(TypeId::of::<String>()))
+        );
+    }
+
+    #[test]
+    fn register_shared_keep_local() {
+        let mut store = TypeComponentStore::default();
+        let source = Entity::from(1);
+        let target = Entity::from(2);
+        let other = Entity::from(3);
+        store.register(source, 5_u32);
+        store.register(target, 10_u32);
+
+        assert_eq!(
+            store.register_shared_keep_local::<u32>(target, source),
+            Err(NotFound::OwnedComponent(target))
+        );
+        assert_eq!(*store.get::<u32>(target).unwrap(), 10);
+        assert!(store.is_origin::<u32>(target));
+
+        assert!(store
+            .register_shared_keep_local::<u32>(other, source)
+            .is_ok());
+        assert_eq!(*store.get::<u32>(other).unwrap(), 5);
+    }
+
+    #[test]
+    fn get_pair_mut() {
+        let mut store = TypeComponentStore::default();
+        let parent = Entity::from(1);
+        let child = Entity::from(2);
+        store.register(parent, 100_u32);
+        store.register(child, 10_u32);
+
+        let (parent_size, child_size) = store.get_pair_mut::<u32>(parent, child).unwrap();
+        *parent_size += *child_size;
+        *child_size = 0;
+
+        assert_eq!(*store.get::<u32>(parent).unwrap(), 110);
+        assert_eq!(*store.get::<u32>(child).unwrap(), 0);
+        assert_eq!(
+            store.get_pair_mut::<u32>(parent, Entity::from(3)).err(),
+            Some(NotFound::Entity(Entity::from(3)))
+        );
+    }
+
+    #[test]
+    fn get_pair_mut_same_entity() {
+        let mut store = TypeComponentStore::default();
+        let source = Entity::from(1);
+        let target = Entity::from(2);
+        store.register(source, 5_u32);
+        store.register_shared::<u32>(target, source);
+
+        assert_eq!(
+            store.get_pair_mut::<u32>(source, source).err(),
+            Some(NotFound::Aliased(TypeId::of::<u32>()))
+        );
+        assert_eq!(
+            store.get_pair_mut::<u32>(source, target).err(),
+            Some(NotFound::Aliased(TypeId::of::<u32>()))
+        );
+    }
+
+    #[test]
+    fn query_changed() {
+        let mut store = TypeComponentStore::default();
+        store.register(Entity(1), 1_u32);
+        store.register(Entity(2), 2_u32);
+        store.register_shared::<u32>(Entity(3), Entity(2));
+        assert_eq!(store.query_changed::<u32>(0).count(), 3);
+
+        store.set_tick(1);
+        assert_eq!(store.query_changed::<u32>(1).count(), 0);
+
+        *store.get_mut::<u32>(Entity(3)).unwrap() += 1;
+
+        let mut changed: Vec<(Entity, &u32)> = store.query_changed::<u32>(1).collect();
+        changed.sort();
+        assert_eq!(changed, vec![(Entity(2), &3), (Entity(3), &3)]);
+    }
+
+    #[test]
+    fn query_changed_shared() {
+        let mut store = TypeComponentStore::default();
+        store.register(Entity(1), 5_u32);
+        store.register_shared::<u32>(Entity(2), Entity(1));
+        store.register(Entity(2), 9_u32);
+        store.register(Entity(3), 7_u32);
+
+        store.set_tick(1);
+        *store.get_mut::<u32>(Entity(3)).unwrap() += 1;
+        store.register_shared::<u32>(Entity(3), Entity(1));
+        assert!(!store
+            .changed
+            .contains_key(&(Entity(3), TypeId::of::<u32>())));
+
+        let mut changed: Vec<(Entity, &u32)> = store.query_changed::<u32>(0).collect();
+        changed.sort();
+        assert_eq!(
+            changed,
+            vec![(Entity(1), &5), (Entity(2), &9), (Entity(3), &5)]
+        );
+        assert_eq!(store.query_changed::<u32>(1).count(), 0);
+    }
+
+    #[test]
+    fn source() {
+        let mut store = TypeComponentStore::default();
+        store.register(Entity(1), 5_u32);
+        store.register_shared::<u32>(Entity(2), Entity(1));
+        store.register_shared::<u32>(Entity(3), Entity(2));
+
+        assert_eq!(store.source::<u32>(Entity(1)), Ok(Entity(1)));
+        assert_eq!(store.source::<u32>(Entity(3)), Ok(Entity(1)));
+        assert_eq!(*store.get::<u32>(Entity(3)).unwrap(), 5);
+        assert_eq!(
+            store.source::<u32>(Entity(4)),
+            Err(NotFound::Entity(Entity(4)))
+        );
+
+        store.register_shared::<u32>(Entity(5), Entity(6));
+        store.register_shared::<u32>(Entity(6), Entity(5));
+        assert_eq!(
+            store.source::<u32>(Entity(5)),
+            Err(NotFound::Entity(Entity(5)))
+        );
+    }
+
+    #[test]
+    fn entities_of_component() {
+        let mut store = TypeComponentStore::default();
+        store.register(Entity(1), 5_u32);
+        store.register(Entity(4), 6_u32);
+        store.register_shared::<u32>(Entity(2), Entity(1));
+        store.register_shared::<u32>(Entity(3), Entity(1));
+        store.register_shared::<u32>(Entity(5), Entity(4));
+
+        let expected = vec![Entity(1), Entity(2), Entity(3)];
+        assert_eq!(store.entities_of_component::<u32>(Entity(1)), expected);
+        assert_eq!(store.entities_of_component::<u32>(Entity(3)), expected);
+        assert!(store.entities_of_component::<f32>(Entity(1)).is_empty());
+    }
+
+    #[test]
+    fn replace_component() {
+        #[derive(Debug, PartialEq)]
+        struct Counter(u32);
+
+        let mut store = TypeComponentStore::default();
+        let entity = Entity::from(1);
+
+        assert_eq!(store.replace_component(entity, Counter(1)), None);
+        assert_eq!(
+            store.replace_component(entity, Counter(2)),
+            Some(Counter(1))
+        );
+        assert_eq!(store.get::<Counter>(entity).unwrap(), &Counter(2));
+    }
+
+    #[test]
+    fn remove_entity_drain() {
+        let mut store = TypeComponentStore::default();
+        let entity = Entity::from(1);
+        store.register(entity, 5_u32);
+        store.register(entity, String::from("Button"));
+        store.register_shared::<f64>(entity, Entity::from(2));
+
+        let drained = store.remove_entity_drain(entity);
+        let mut type_ids = vec![TypeId::of::<u32>(), TypeId::of::<String>()];
+        type_ids.sort();
+
+        assert_eq!(
+            drained
+                .iter()
+                .map(|(type_id, _)| *type_id)
+                .collect::<Vec<_>>(),
+            type_ids
+        );
+        for (type_id, component) in &drained {
+            if *type_id == TypeId::of::<u32>() {
+                assert_eq!(component.downcast_ref::<u32>(), Some(&5));
+            } else {
+                assert_eq!(
+                    component.downcast_ref::<String>(),
+                    Some(&String::from("Button"))
+                );
+            }
+        }
+        assert!(!store.contains_entity(entity));
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn entities() {
+        let mut store = TypeComponentStore::default();
+        assert!(store.entities().is_empty());
+        store.register(Entity::from(3), 5_u32);
+        store.register(Entity::from(1), 2_f64);
+        store.register(Entity::from(1), 3_u32);
+        store.register_shared::<u32>(Entity::from(4), Entity::from(3));
+        store.register_shared::<f64>(Entity::from(3), Entity::from(1));
+
+        assert_eq!(
+            store.entities(),
+            vec![Entity::from(1), Entity::from(3), Entity::from(4)]
+        );
+    }
+
+    #[test]
+    fn builder_with_many() {
+        let components = vec![
+            ComponentBox::new(5_u32),
+            ComponentBox::new(String::from("Test")),
+        ];
+        let (map, _) = TypeComponentBuilder::new()
+            .with(2_f64)
+            .with_many(components)
+            .build();
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map[&TypeId::of::<u32>()].downcast_ref::<u32>(), Some(&5));
+        assert!(map.contains_key(&TypeId::of::<String>()));
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let mut store = TypeComponentStore::default();
+        let entity = Entity::from(1);
+        let (mut components, shared) = TypeComponentBuilder::new().build();
+        components.insert(TypeId::of::<u32>(), Box::new(String::from("Test")));
+        store.append(entity, (components, shared));
+
+        let mismatch = NotFound::TypeMismatch(TypeId::of::<u32>());
+        assert_eq!(store.get::<u32>(entity), Err(mismatch.clone()));
+        assert_eq!(store.get_mut::<u32>(entity).map(|c| *c), Err(mismatch));
+        assert_eq!(store.query::<u32>().count(), 0);
+        assert_eq!(store.replace_component(entity, 5_u32), None);
+        assert_eq!(store.get::<u32>(entity), Ok(&5));
+    }
+
+    #[test]
+    fn get_or_default() {
+        #[derive(Default, Debug, PartialEq)]
+        struct Counter(u32);
+
+        let mut registry = DefaultRegistry::new();
+        registry.register::<Counter>();
+        let mut store = TypeComponentStore::default();
+        let entity = Entity::from(1);
+        store.register(Entity::from(2), Counter(3));
+        store.register_shared::<Counter>(entity, Entity::from(3));
+
+        assert_eq!(
+            store.get_or_default::<Counter>(entity, &registry),
+            Ok(&Counter(0))
+        );
+        assert!(store.shared_entities().is_empty());
+        assert!(store.check_shared(entity).is_ok());
+        store.get_mut::<Counter>(entity).unwrap().0 += 1;
+        assert_eq!(
+            store.get_or_default::<Counter>(entity, &registry),
+            Ok(&Counter(1))
+        );
+        assert_eq!(
+            store.get_or_default::<Counter>(Entity::from(2), &registry),
+            Ok(&Counter(3))
+        );
+        assert_eq!(
+            store.get_or_default::<u32>(entity, &registry),
+            Err(NotFound::Component(TypeId::of::<u32>()))
+        );
+    }
+
+    #[test]
+    fn max_share_depth() {
+        let mut store = TypeComponentStore::default();
+        store.register(Entity(0), 5_u32);
+        for id in 1..=MAX_SHARE_DEPTH as u32 + 1 {
+            store.register_shared::<u32>(Entity(id), Entity(id - 1));
+        }
+
+        assert_eq!(store.get::<u32>(Entity(3)), Ok(&5));
+        assert_eq!(
+            store.source::<u32>(Entity(MAX_SHARE_DEPTH as u32)),
+            Ok(Entity(0))
+        );
+        let too_deep = Entity(MAX_SHARE_DEPTH as u32 + 1);
+        assert_eq!(
+            store.source::<u32>(too_deep),
+            Err(NotFound::Entity(too_deep))
+        );
+        assert!(store.get::<u32>(too_deep).is_err());
+    }
+
+    #[test]
+    fn split_mut() {
+        #[derive(Debug, PartialEq)]
+        struct Position(i32);
+
+        let mut store = TypeComponentStore::default();
+        let leader = Entity(0);
+        let follower = Entity(1);
+        store.register(leader, Position(10));
+        store.register(leader, 3_u32);
+        store.register(follower, Position(0));
+        store.register_shared::<u32>(follower, leader);
+
+        {
+            let (positions, speeds) = store.split_mut::<Position, u32>().unwrap();
+            assert_eq!(positions.len(), 2);
+            assert_eq!(speeds.iter().count(), 1);
+            for (entity, position) in positions {
+                position.0 += *speeds.get(entity).unwrap() as i32;
+            }
+            assert!(speeds.get(Entity(2)).is_err());
+        }
+
+        assert_eq!(store.get::<Position>(leader), Ok(&Position(13)));
+        assert_eq!(store.get::<Position>(follower), Ok(&Position(3)));
+        assert_eq!(store.get::<u32>(follower), Ok(&3));
+    }
+
+    #[test]
+    fn split_mut_same_type() {
+        let mut store = TypeComponentStore::default();
+        store.register(Entity(0), 5_u32);
+
+        assert!(matches!(
+            store.split_mut::<u32, u32>(),
+            Err(NotFound::Aliased(type_id)) if type_id == TypeId::of::<u32>()
+        ));
+    }
+
+    #[test]
+    fn register_over_shared() {
+        let mut store = TypeComponentStore::default();
+        store.register(Entity(0), 5_u32);
+        store.register(Entity(0), String::from("x"));
+        store.register(Entity(1), 7_u32);
+        store.register_shared::<u32>(Entity(1), Entity(0));
+        store.register(Entity(1), 9_u32);
+        store.register_shared::<String>(Entity(1), Entity(0));
+        store.register_shared::<u32>(Entity(2), Entity(0));
+        store.register_box(Entity(2), ComponentBox::new(3_u32));
+        store.register_shared::<String>(Entity(2), Entity(0));
+
+        let mut entities: Vec<(Entity, u32, String)> = store
+            .query2::<u32, String>()
+            .map(|(entity, a, b)| (entity, *a, b.clone()))
+            .collect();
+        entities.sort_by_key(|(entity, _, _)| *entity);
+
+        assert_eq!(
+            entities,
+            vec![
+                (Entity(0), 5, String::from("x")),
+                (Entity(1), 9, String::from("x")),
+                (Entity(2), 3, String::from("x"))
+            ]
+        );
+        assert!(store.is_origin::<u32>(Entity(1)));
+        assert_eq!(store.source::<u32>(Entity(2)), Ok(Entity(2)));
+    }
 }
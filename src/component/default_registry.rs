@@ -0,0 +1,109 @@
+use core::any::{Any, TypeId};
+
+#[cfg(not(feature = "no_std"))]
+use std::collections::HashMap;
+
+#[cfg(feature = "no_std")]
+use hashbrown::HashMap;
+
+#[cfg(feature = "no_std")]
+use alloc::{boxed::Box, string::String};
+
+use super::Component;
+use crate::error::NotFound;
+
+type DefaultFn = Box<dyn Fn() -> Box<dyn Any>>;
+
+/// The `DefaultRegistry` stores how to create default components by component type or by
+/// component key. It is used by `get_or_default` of the component stores for optional
+/// components that have a sensible default.
+#[derive(Default)]
+pub struct DefaultRegistry {
+    defaults: HashMap<TypeId, DefaultFn>,
+    key_defaults: HashMap<String, DefaultFn>,
+}
+
+impl DefaultRegistry {
+    /// Creates an new registry with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `C::default` as default of the component type `C`.
+    pub fn register<C: Component + Default>(&mut self) {
+        self.register_with(C::default);
+    }
+
+    /// Registers the `default` function of the component type `C`.
+    pub fn register_with<C: Component>(&mut self, default: impl Fn() -> C + 'static) {
+        self.defaults
+            .insert(TypeId::of::<C>(), Box::new(move || Box::new(default())));
+    }
+
+    /// Registers the `default` function of the component `key`. A key default takes precedence
+    /// over the default of the component type.
+    pub fn register_key<C: Component>(&mut self, key: &str, default: impl Fn() -> C + 'static) {
+        self.key_defaults
+            .insert(key.into(), Box::new(move || Box::new(default())));
+    }
+
+    /// Returns `true` if a default of the component type `C` is registered.
+    pub fn contains<C: Component>(&self) -> bool {
+        self.defaults.contains_key(&TypeId::of::<C>())
+    }
+
+    /// Returns `true` if a default of the component `key` is registered.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.key_defaults.contains_key(key)
+    }
+
+    /// Creates the default component of the given `type_id`. If no default is registered
+    /// `NotFound::Component` will be returned.
+    pub fn default_component(&self, type_id: TypeId) -> Result<Box<dyn Any>, NotFound> {
+        self.defaults
+            .get(&type_id)
+            .map(|default| default())
+            .ok_or(NotFound::Component(type_id))
+    }
+
+    /// Creates the default component of the given `key`. If no default is registered
+    /// `NotFound::ComponentKey` will be returned.
+    pub fn default_for_key(&self, key: &str) -> Result<Box<dyn Any>, NotFound> {
+        self.key_defaults
+            .get(key)
+            .map(|default| default())
+            .ok_or_else(|| NotFound::ComponentKey(key.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default, Debug, PartialEq)]
+    struct Counter(u32);
+
+    #[test]
+    fn default_component() {
+        let mut registry = DefaultRegistry::new();
+        registry.register::<Counter>();
+        registry.register_key("size", || 5_u32);
+        assert!(registry.contains::<Counter>());
+        assert!(registry.contains_key("size"));
+        assert!(!registry.contains::<u32>());
+
+        let counter = registry.default_component(TypeId::of::<Counter>()).unwrap();
+        assert_eq!(counter.downcast_ref::<Counter>(), Some(&Counter(0)));
+        let size = registry.default_for_key("size").unwrap();
+        assert_eq!(size.downcast_ref::<u32>(), Some(&5));
+
+        assert_eq!(
+            registry.default_component(TypeId::of::<u32>()).err(),
+            Some(NotFound::Component(TypeId::of::<u32>()))
+        );
+        assert_eq!(
+            registry.default_for_key("width").err(),
+            Some(NotFound::ComponentKey(String::from("width")))
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{entity::Entity, error::NotFound};
+
+use super::{Component, TypeComponentStore};
+
+/// Read-only handle to the components of one entity of a `TypeComponentStore`.
+pub struct EntityRef<'a> {
+    entity: Entity,
+    store: &'a TypeComponentStore,
+}
+
+impl<'a> EntityRef<'a> {
+    /// Creates a new handle for the given `entity`.
+    pub fn new(entity: Entity, store: &'a TypeComponentStore) -> Self {
+        EntityRef { entity, store }
+    }
+
+    /// Returns the entity of the handle.
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Returns a reference of the component of type `C`.
+    pub fn get<C: Component>(&self) -> Result<&'a C, NotFound> {
+        self.store.get::<C>(self.entity)
+    }
+
+    /// Returns `true` if the entity owns or shares a component of type `C`.
+    pub fn has<C: Component>(&self) -> bool {
+        self.store.contains_component::<C>(self.entity)
+    }
+}
+
+/// Mutable handle to the components of one entity of a `TypeComponentStore`.
+pub struct EntityRefMut<'a> {
+    entity: Entity,
+    store: &'a mut TypeComponentStore,
+}
+
+impl<'a> EntityRefMut<'a> {
+    /// Creates a new mutable handle for the given `entity`.
+    pub fn new(entity: Entity, store: &'a mut TypeComponentStore) -> Self {
+        EntityRefMut { entity, store }
+    }
+
+    /// Returns the entity of the handle.
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Returns a reference of the component of type `C`.
+    pub fn get<C: Component>(&self) -> Result<&C, NotFound> {
+        self.store.get::<C>(self.entity)
+    }
+
+    /// Returns a mutable reference of the component of type `C`.
+    pub fn get_mut<C: Component>(&mut self) -> Result<&mut C, NotFound> {
+        self.store.get_mut::<C>(self.entity)
+    }
+
+    /// Sets the component of type `C`. A shared component is written to its source, otherwise
+    /// the component is registered on the entity.
+    pub fn set<C: Component>(&mut self, component: C) {
+        match self.store.get_mut::<C>(self.entity) {
+            Ok(current) => *current = component,
+            Err(_) => self.store.register(self.entity, component),
+        }
+    }
+
+    /// Returns `true` if the entity owns or shares a component of type `C`.
+    pub fn has<C: Component>(&self) -> bool {
+        self.store.contains_component::<C>(self.entity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entity_ref() {
+        let mut store = TypeComponentStore::default();
+        let entity = Entity::from(1);
+        store.register(entity, 5_u32);
+
+        let entity_ref = EntityRef::new(entity, &store);
+
+        assert_eq!(entity_ref.entity(), entity);
+        assert_eq!(entity_ref.get::<u32>(), Ok(&5));
+        assert!(entity_ref.has::<u32>());
+        assert!(!entity_ref.has::<f64>());
+        assert!(entity_ref.get::<f64>().is_err());
+    }
+
+    #[test]
+    fn entity_ref_mut() {
+        let mut store = TypeComponentStore::default();
+        let source = Entity::from(1);
+        let target = Entity::from(2);
+        store.register(source, 5_u32);
+        store.register_shared::<u32>(target, source);
+
+        let mut entity_ref = EntityRefMut::new(target, &mut store);
+        entity_ref.set(7_u32);
+        entity_ref.set(String::from("Test"));
+        *entity_ref.get_mut::<String>().unwrap() += "ed";
+
+        assert!(entity_ref.has::<String>());
+        assert_eq!(entity_ref.get::<String>(), Ok(&String::from("Tested")));
+        assert_eq!(store.get::<u32>(source), Ok(&7));
+        assert!(store.is_origin::<String>(target));
+    }
+}
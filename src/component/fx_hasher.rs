@@ -0,0 +1,94 @@
+use core::hash::{BuildHasherDefault, Hasher};
+
+#[cfg(not(feature = "no_std"))]
+use std::collections::HashMap;
+
+#[cfg(feature = "no_std")]
+use hashbrown::HashMap;
+
+/// Hash map that uses the `FxHasher`.
+pub(crate) type FxHashMap<K, V> = HashMap<K, V, BuildHasherDefault<FxHasher>>;
+
+const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+/// Fast non-cryptographic hasher of the rustc compiler. It is used for the small `(Entity, TypeId)`
+/// keys of the `TypeComponentStore`, where the default SipHash hasher is slow.
+#[derive(Default, Clone, Copy)]
+pub(crate) struct FxHasher {
+    hash: u64,
+}
+
+impl FxHasher {
+    fn add_to_hash(&mut self, i: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ i).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut buffer = [0; 8];
+            buffer[..chunk.len()].copy_from_slice(chunk);
+            self.add_to_hash(u64::from_le_bytes(buffer));
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add_to_hash(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Entity;
+    use core::{
+        any::TypeId,
+        hash::{BuildHasher, Hash},
+    };
+
+    fn hash(value: impl Hash) -> u64 {
+        BuildHasherDefault::<FxHasher>::default().hash_one(value)
+    }
+
+    #[test]
+    fn hasher() {
+        assert_eq!(hash(5_u32), hash(5_u32));
+        assert_ne!(hash(5_u32), hash(6_u32));
+        assert_ne!(hash("size"), hash("sizes"));
+    }
+
+    #[test]
+    fn map() {
+        let mut map = FxHashMap::default();
+        for id in 0..100 {
+            map.insert((Entity(id), TypeId::of::<u32>()), id);
+            map.insert((Entity(id), TypeId::of::<f64>()), id * 2);
+        }
+
+        assert_eq!(map.len(), 200);
+        assert_eq!(map.get(&(Entity(7), TypeId::of::<u32>())), Some(&7));
+        assert_eq!(map.get(&(Entity(7), TypeId::of::<f64>())), Some(&14));
+        assert_eq!(map.get(&(Entity(7), TypeId::of::<String>())), None);
+    }
+}
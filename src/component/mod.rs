@@ -1,15 +1,34 @@
-use core::any::{Any, TypeId};
+use core::{
+    any::{Any, TypeId},
+    cell::{RefCell, RefMut},
+    ops::Range,
+};
 
 #[cfg(feature = "no_std")]
-use alloc::collections::{BTreeMap, HashMap};
+use alloc::{boxed::Box, string::String, vec::Vec};
 
-use crate::entity::*;
+use crate::{commands::Commands, entity::*, error::NotFound};
 
+pub use self::archetype_component_store::*;
+pub use self::clone_registry::*;
 pub use self::component_store::*;
+pub use self::default_registry::*;
+pub use self::entity_ref::*;
+#[cfg(feature = "serde")]
+pub use self::serialize_registry::*;
 pub use self::string_component_store::*;
+pub use self::type_name_registry::*;
 
+mod archetype_component_store;
+mod clone_registry;
 mod component_store;
+mod default_registry;
+mod entity_ref;
+mod fx_hasher;
+#[cfg(feature = "serde")]
+mod serialize_registry;
 mod string_component_store;
+mod type_name_registry;
 
 /// The entity builder is used to create an entity with components.
 pub struct EntityBuilder<'a, E, C>
@@ -32,18 +51,46 @@ where
     E: EntityStore,
     C: ComponentStore,
 {
-    pub fn components(self, components: C::Components) -> Self {
-        self.component_store.append(self.entity, components);
-        self
+    /// Returns the id of the entity. The id does not change while the builder is used and equals
+    /// the entity returned by `build`, e.g. to share a component of the entity itself.
+    pub fn entity(&self) -> Entity {
+        self.entity
     }
+
     /// Finishing the creation of the entity.
     pub fn build(self) -> Entity {
         self.entity_store.register_entity(self.entity);
         // self.component_store.register_entity(self.entity);
         self.entity
     }
+
+    /// Finishing the creation of the entity like `build`, but first checks that every shared
+    /// component resolves to an owner. Otherwise the components of the entity are removed and
+    /// the error is returned.
+    pub fn build_checked(self) -> Result<Entity, NotFound> {
+        if let Err(error) = self.component_store.check_shared(self.entity) {
+            self.component_store.remove_entity(self.entity);
+            return Err(error);
+        }
+
+        Ok(self.build())
+    }
+}
+
+impl<'a, E, C> EntityBuilder<'a, E, C>
+where
+    E: EntityStore,
+    C: AppendComponents,
+{
+    pub fn components(self, components: C::Components) -> Self {
+        self.component_store.append(self.entity, components);
+        self
+    }
 }
 
+/// Counts the runs of a world. Used to detect changed components.
+pub type Tick = u64;
+
 /// This trait is used to internal handle all components types. This trait is implicitly implemented for all other types.
 pub trait Component: Any {}
 impl<E: Any> Component for E {}
@@ -91,7 +138,6 @@ impl ComponentBox {
 }
 
 /// The EntityComponentManager represents the main entity and component storage.
-#[derive(Default)]
 pub struct EntityComponentManager<E, C>
 where
     E: EntityStore,
@@ -102,6 +148,22 @@ where
     entity_store: E,
 
     entity_counter: u32,
+
+    commands: RefCell<Commands<E, C>>,
+
+    remove_entity_hooks: Vec<Box<dyn FnMut(Entity)>>,
+
+    system_entities: Option<Vec<Entity>>,
+}
+
+impl<E, C> Default for EntityComponentManager<E, C>
+where
+    E: EntityStore + Default,
+    C: ComponentStore + Default,
+{
+    fn default() -> Self {
+        EntityComponentManager::new(E::default(), C::default())
+    }
 }
 
 impl<E, C> EntityComponentManager<E, C>
@@ -115,6 +177,9 @@ where
             entity_counter: 0,
             component_store,
             entity_store,
+            commands: RefCell::new(Commands::default()),
+            remove_entity_hooks: vec![],
+            system_entities: None,
         }
     }
 
@@ -149,9 +214,16 @@ where
     }
 
     /// Creates a new entity and returns a returns an `TypeEntityBuilder`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if all entity ids are used. The id of `Entity::INVALID` is never created.
     pub fn create_entity(&mut self) -> EntityBuilder<'_, E, C> {
-        let entity: Entity = self.entity_counter.into();
-        self.entity_counter += 1;
+        let entity: Entity = self
+            .allocate_range(1)
+            .expect("EntityComponentManager.create_entity: all entity ids are used")
+            .start
+            .into();
 
         EntityBuilder {
             entity,
@@ -160,6 +232,35 @@ where
         }
     }
 
+    /// Reserves `n` consecutive entity ids and returns them as range. The entities are not
+    /// registered, use `register_entity` and the component store to set them up. The ids end
+    /// before the id of `Entity::INVALID`. If not enough ids are left `None` will be returned and
+    /// no id is reserved.
+    pub fn allocate_range(&mut self, n: u32) -> Option<Range<u32>> {
+        let start = self.entity_counter;
+        self.entity_counter = start.checked_add(n)?;
+        Some(start..self.entity_counter)
+    }
+
+    /// Returns the id of the next entity created by `create_entity`.
+    pub fn entity_counter(&self) -> u32 {
+        self.entity_counter
+    }
+
+    // Replaces the stores and the entity counter and drops all queued commands.
+    pub(crate) fn restore(&mut self, entity_store: E, component_store: C, entity_counter: u32) {
+        self.entity_store = entity_store;
+        self.component_store = component_store;
+        self.entity_counter = entity_counter;
+        self.commands.borrow_mut().drain();
+    }
+
+    /// Returns `true` if the given `entity` is registered in the entity store. Removed and never
+    /// created entities return `false`.
+    pub fn contains_entity(&self, entity: Entity) -> bool {
+        self.entity_store.contains(entity)
+    }
+
     /// Register a new `entity`.
     pub fn register_entity(&mut self, entity: impl Into<Entity>) {
         let entity = entity.into();
@@ -167,26 +268,575 @@ where
         // self.component_store.register_entity(entity);
     }
 
+    /// Registers a `hook` that is called with the entity each time an entity is removed. Hooks
+    /// are called in registration order before the components of the entity are dropped.
+    pub fn on_remove_entity(&mut self, hook: impl FnMut(Entity) + 'static) {
+        self.remove_entity_hooks.push(Box::new(hook));
+    }
+
     /// Removes a `entity` from the manager.
     pub fn remove_entity(&mut self, entity: impl Into<Entity>) {
         let entity = entity.into();
+
+        for hook in &mut self.remove_entity_hooks {
+            hook(entity);
+        }
+
         self.component_store.remove_entity(entity);
         self.entity_store.remove_entity(entity);
     }
+
+    /// Returns the entities of the running system. If the system has a filter only the entities
+    /// that pass it are returned, otherwise all entities of the entity store. If the system has
+    /// a sort the entities are returned in its order.
+    pub fn system_entities(&self) -> Vec<Entity> {
+        match &self.system_entities {
+            Some(entities) => entities.clone(),
+            None => self.entity_store.entities(),
+        }
+    }
+
+    // Sets the entities returned by `system_entities`. `None` resets to all entities.
+    pub(crate) fn set_system_entities(&mut self, entities: Option<Vec<Entity>>) {
+        self.system_entities = entities;
+    }
+
+    /// Removes all entities for which `f` returns `false` with their components in one pass.
+    /// Removal hooks run for each removed entity and shared links that point to a removed entity
+    /// are removed too.
+    pub fn retain_entities(&mut self, f: impl Fn(Entity, &C) -> bool) {
+        let mut removed: Vec<Entity> = self
+            .entity_store
+            .entities()
+            .into_iter()
+            .filter(|entity| !f(*entity, &self.component_store))
+            .collect();
+        removed.sort();
+
+        for entity in &removed {
+            for hook in &mut self.remove_entity_hooks {
+                hook(*entity);
+            }
+        }
+
+        self.component_store.remove_entities(&removed);
+        self.entity_store
+            .retain(|entity| removed.binary_search(entity).is_err());
+    }
+
+    /// Returns the command queue. Queued commands are applied with `apply_commands`, which
+    /// `World::run` calls after each system run.
+    pub fn commands(&self) -> RefMut<'_, Commands<E, C>> {
+        self.commands.borrow_mut()
+    }
+
+    /// Applies all queued commands in queue order. Commands queued while applying are applied
+    /// too.
+    pub fn apply_commands(&mut self) {
+        while !self.commands.get_mut().is_empty() {
+            let commands = self.commands.get_mut().drain();
+
+            for command in commands {
+                command(self);
+            }
+        }
+    }
+
+    /// Reserves capacity for at least `additional` more components in the component store. The
+    /// store grows amortized on its own, but reserving up front avoids repeated rehashing when a
+    /// large number of components is added at once.
+    pub fn reserve(&mut self, additional: usize) {
+        self.component_store.reserve(additional);
+    }
+
+    /// Calls `f` for each registered entity with mutable access to the component store. The
+    /// entity list is collected once before the first call.
+    pub fn for_each_entity_mut(&mut self, mut f: impl FnMut(Entity, &mut C)) {
+        for entity in self.entity_store.entities() {
+            f(entity, &mut self.component_store);
+        }
+    }
+
+    /// Calls `f` for each registered entity with mutable access to the component store and
+    /// removes the entities for which `f` returns `false`. The entities are visited in place with
+    /// `EntityStore::retain`, so the entity list is not cloned. Removal hooks run after all
+    /// entities are visited.
+    pub fn retain_and_update(&mut self, mut f: impl FnMut(Entity, &mut C) -> bool) {
+        let component_store = &mut self.component_store;
+        let mut removed = vec![];
+
+        self.entity_store.retain(|entity| {
+            let keep = f(*entity, component_store);
+            if !keep {
+                removed.push(*entity);
+            }
+            keep
+        });
+
+        for entity in &removed {
+            for hook in &mut self.remove_entity_hooks {
+                hook(*entity);
+            }
+        }
+
+        self.component_store.remove_entities(&removed);
+    }
 }
 
-/// This trait is used to define a custom component store.
-pub trait ComponentStore {
-    type Components;
+impl<E, C> EntityComponentManager<E, C>
+where
+    E: EntityStore,
+    C: ManageEntities,
+{
+    /// Returns all entities that share at least one component of another entity, sorted by id.
+    pub fn shared_entities(&self) -> Vec<Entity> {
+        self.component_store.shared_entities()
+    }
 
-    fn append(&mut self, entity: Entity, components: Self::Components);
+    /// Removes all entities with their components but keeps the allocated capacity. Removal
+    /// hooks run for each entity. If `reset_counter` is `true` the next created entity starts
+    /// with id 0 again, otherwise ids continue after the last created entity.
+    pub fn clear(&mut self, reset_counter: bool) {
+        for entity in self.entity_store.entities() {
+            for hook in &mut self.remove_entity_hooks {
+                hook(entity);
+            }
+        }
+
+        self.component_store.clear();
+        self.entity_store.clear();
+
+        if reset_counter {
+            self.entity_counter = 0;
+        }
+    }
+
+    /// Swaps all components and shared links of the entities `a` and `b`, including links that
+    /// point to them. The entity store is not changed.
+    pub fn swap_entities(&mut self, a: Entity, b: Entity) {
+        self.component_store.swap_entities(a, b);
+    }
+}
+
+impl<E> EntityComponentManager<E, TypeComponentStore>
+where
+    E: EntityStore,
+{
+    /// Returns a read-only handle to the components of the given `entity`.
+    pub fn entity(&self, entity: impl Into<Entity>) -> EntityRef<'_> {
+        EntityRef::new(entity.into(), &self.component_store)
+    }
 
+    /// Returns a mutable handle to the components of the given `entity`.
+    pub fn entity_mut(&mut self, entity: impl Into<Entity>) -> EntityRefMut<'_> {
+        EntityRefMut::new(entity.into(), &mut self.component_store)
+    }
+}
+
+/// This trait is used to define a custom component store. It is object safe, so stores could be
+/// used as `Box<dyn ComponentStore>`.
+pub trait ComponentStore {
     // /// Registers an new entity on the store.
     // fn register_entity(&mut self, entity: impl Into<Entity>);
 
-    /// Removes and entity from the store.
-    fn remove_entity(&mut self, entity: impl Into<Entity>);
+    /// Removes and entity from the store. Shared links that point to the entity are removed too.
+    fn remove_entity(&mut self, entity: Entity);
+
+    /// Removes the given `entities` with their components. Shared links that point to one of
+    /// the entities are removed too.
+    fn remove_entities(&mut self, entities: &[Entity]) {
+        for entity in entities {
+            self.remove_entity(*entity);
+        }
+    }
 
     /// Print infos about the given entity.
-    fn print_entity(&self, entity: impl Into<Entity>);
+    #[cfg(not(feature = "no_std"))]
+    fn print_entity(&self, entity: Entity);
+
+    /// Describes the owned and shared components of the given `entity`, one component per line.
+    /// The `type_names` are used by stores that key components by type. Stores without a
+    /// description return an empty string.
+    fn describe_entity(&self, _entity: Entity, _type_names: &TypeNameRegistry) -> String {
+        String::new()
+    }
+
+    /// Returns an error if a shared component of the given `entity` has no owner. Stores
+    /// without shared components always return `Ok`.
+    fn check_shared(&self, _entity: Entity) -> Result<(), NotFound> {
+        Ok(())
+    }
+
+    /// Reserves capacity for at least `additional` more components. Stores without
+    /// preallocation ignore it.
+    fn reserve(&mut self, _additional: usize) {}
+
+    /// Sets the current `tick` that marks changed components. Stores without change detection
+    /// ignore it.
+    fn set_tick(&mut self, _tick: Tick) {}
+
+    /// Returns the number of components in the store.
+    fn len(&self) -> usize;
+
+    /// Returns true if the components are empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// This trait is used by component stores that could list, swap and clear their entities.
+pub trait ManageEntities: ComponentStore {
+    /// Returns all entities that own or share at least one component, sorted by id.
+    fn entities(&self) -> Vec<Entity>;
+
+    /// Returns all entities that share at least one component, sorted by id.
+    fn shared_entities(&self) -> Vec<Entity>;
+
+    /// Swaps all components and shared links of the entities `a` and `b`. Shared links that
+    /// point to one of them point to the other afterwards.
+    fn swap_entities(&mut self, a: Entity, b: Entity);
+
+    /// Removes all components and shared links but keeps the allocated capacity.
+    fn clear(&mut self);
+}
+
+/// This trait is used to append a set of components built by the store's component builder.
+pub trait AppendComponents: ComponentStore {
+    /// The set of components that is appended at once.
+    type Components;
+
+    /// Appends the given `components` to the `entity`.
+    fn append(&mut self, entity: Entity, components: Self::Components);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    fn component_count<C: ComponentStore>(store: &C) -> (usize, bool) {
+        (store.len(), store.is_empty())
+    }
+
+    #[test]
+    fn component_store_len() {
+        let mut type_store = TypeComponentStore::default();
+        assert_eq!(component_count(&type_store), (0, true));
+        type_store.register(Entity(0), 5_u32);
+        type_store.register(Entity(1), 5_u32);
+        assert_eq!(component_count(&type_store), (2, false));
+
+        let mut string_store = StringComponentStore::default();
+        assert_eq!(component_count(&string_store), (0, true));
+        string_store.register("value", Entity(0), 5_u32);
+        assert_eq!(component_count(&string_store), (1, false));
+    }
+
+    #[test]
+    fn allocate_range() {
+        let mut ecm =
+            EntityComponentManager::new(VecEntityStore::default(), TypeComponentStore::default());
+        ecm.create_entity().build();
+
+        let range = ecm.allocate_range(10);
+
+        assert_eq!(range, Some(1..11));
+        assert_eq!(ecm.create_entity().build(), Entity(11));
+        assert_eq!(ecm.allocate_range(0), Some(12..12));
+
+        assert_eq!(ecm.allocate_range(u32::MAX - 11), None);
+        assert_eq!(ecm.entity_counter(), 12);
+        assert_eq!(ecm.allocate_range(u32::MAX - 13), Some(12..u32::MAX - 1));
+        assert_eq!(ecm.create_entity().build(), Entity(u32::MAX - 1));
+        assert_eq!(ecm.allocate_range(1), None);
+        assert_eq!(ecm.entity_counter(), Entity::INVALID.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "all entity ids are used")]
+    fn create_entity_exhausted() {
+        let mut ecm =
+            EntityComponentManager::new(VecEntityStore::default(), TypeComponentStore::default());
+        assert_eq!(ecm.allocate_range(u32::MAX), Some(0..u32::MAX));
+
+        ecm.create_entity();
+    }
+
+    #[test]
+    fn on_remove_entity() {
+        let mut ecm =
+            EntityComponentManager::new(VecEntityStore::default(), TypeComponentStore::default());
+        let first = ecm
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(1_u32).build())
+            .build();
+        let second = ecm.create_entity().build();
+        let removed = Rc::new(RefCell::new(vec![]));
+        let hook_removed = removed.clone();
+        ecm.on_remove_entity(move |entity| hook_removed.borrow_mut().push(entity));
+
+        ecm.remove_entity(first);
+        ecm.commands().remove_entity(second);
+        ecm.apply_commands();
+
+        assert_eq!(*removed.borrow(), vec![first, second]);
+    }
+
+    #[test]
+    fn for_each_entity_mut() {
+        let mut ecm =
+            EntityComponentManager::new(VecEntityStore::default(), TypeComponentStore::default());
+        let first = ecm
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(1_u32).build())
+            .build();
+        let second = ecm
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(2_u32).build())
+            .build();
+        let third = ecm.create_entity().build();
+
+        let mut visited = vec![];
+        ecm.for_each_entity_mut(|entity, store| {
+            visited.push(entity);
+            if let Ok(value) = store.get_mut::<u32>(entity) {
+                *value += 10;
+            }
+        });
+
+        assert_eq!(visited, vec![first, second, third]);
+        assert_eq!(*ecm.component_store().get::<u32>(first).unwrap(), 11);
+        assert_eq!(*ecm.component_store().get::<u32>(second).unwrap(), 12);
+    }
+
+    #[test]
+    fn shared_entities() {
+        let mut ecm =
+            EntityComponentManager::new(VecEntityStore::default(), StringComponentStore::default());
+        let source = ecm.create_entity().build();
+        let first = ecm.create_entity().build();
+        let second = ecm.create_entity().build();
+        let (_, store) = ecm.stores_mut();
+        store.register("name", source, String::from("Button"));
+        store.register("depth", source, 1_u32);
+        store.register("name", first, String::from("CheckBox"));
+        store.register_shared::<String>("name", second, source);
+        store.register_shared::<u32>("depth", second, source);
+
+        assert_eq!(ecm.shared_entities(), vec![second]);
+    }
+
+    #[test]
+    fn entity_builder_entity() {
+        let mut ecm =
+            EntityComponentManager::new(VecEntityStore::default(), TypeComponentStore::default());
+        ecm.create_entity().build();
+
+        let builder = ecm.create_entity();
+        let entity = builder.entity();
+        let builder = builder.components(TypeComponentBuilder::new().with(5_u32).build());
+        assert_eq!(builder.entity(), entity);
+
+        assert_eq!(builder.build(), entity);
+        assert_eq!(entity, Entity(1));
+    }
+
+    #[test]
+    fn swap_entities() {
+        let mut ecm =
+            EntityComponentManager::new(VecEntityStore::default(), TypeComponentStore::default());
+        let a = ecm
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(1_u32).build())
+            .build();
+        let b = ecm
+            .create_entity()
+            .components(
+                TypeComponentBuilder::new()
+                    .with(2_u32)
+                    .with(0.5_f32)
+                    .build(),
+            )
+            .build();
+        let target = ecm
+            .create_entity()
+            .components(TypeComponentBuilder::new().with_shared::<u32>(b).build())
+            .build();
+
+        ecm.swap_entities(a, b);
+
+        let store = ecm.component_store();
+        assert_eq!(*store.get::<u32>(a).unwrap(), 2);
+        assert_eq!(*store.get::<u32>(b).unwrap(), 1);
+        assert!(store.get::<f32>(a).is_ok());
+        assert!(store.get::<f32>(b).is_err());
+        assert_eq!(*store.get::<u32>(target).unwrap(), 2);
+    }
+
+    #[test]
+    fn retain_and_update() {
+        let mut ecm =
+            EntityComponentManager::new(VecEntityStore::default(), TypeComponentStore::default());
+        let entities: Vec<Entity> = (0..4_u32)
+            .map(|i| {
+                ecm.create_entity()
+                    .components(TypeComponentBuilder::new().with(i).build())
+                    .build()
+            })
+            .collect();
+
+        ecm.retain_and_update(|entity, store| {
+            let counter = store.get_mut::<u32>(entity).unwrap();
+            *counter += 10;
+            *counter % 2 == 0
+        });
+
+        let (entity_store, component_store) = ecm.stores();
+        assert_eq!(entity_store.inner, vec![entities[0], entities[2]]);
+        assert_eq!(*component_store.get::<u32>(entities[2]).unwrap(), 12);
+        assert!(component_store.get::<u32>(entities[1]).is_err());
+        assert_eq!(component_store.len(), 2);
+    }
+
+    #[test]
+    fn entity_ref() {
+        let mut ecm: EntityComponentManager<VecEntityStore, TypeComponentStore> =
+            EntityComponentManager::default();
+        let entity = ecm
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(5_u32).build())
+            .build();
+
+        ecm.entity_mut(entity).set(String::from("Test"));
+        *ecm.entity_mut(entity).get_mut::<u32>().unwrap() += 1;
+
+        let entity_ref = ecm.entity(entity);
+        assert_eq!(entity_ref.get::<u32>(), Ok(&6));
+        assert!(entity_ref.has::<String>());
+        assert!(!ecm.entity(Entity(9)).has::<u32>());
+    }
+
+    #[test]
+    fn dyn_component_store() {
+        let entity = Entity(1);
+        let mut type_store = TypeComponentStore::default();
+        type_store.register(entity, 5_u32);
+        let mut string_store = StringComponentStore::default();
+        string_store.register("size", entity, 5_u32);
+        string_store.register("name", Entity(2), String::from("Button"));
+
+        let mut stores: Vec<Box<dyn ManageEntities>> =
+            vec![Box::new(type_store), Box::new(string_store)];
+
+        for store in &mut stores {
+            store.remove_entity(entity);
+        }
+
+        assert_eq!(stores[0].len(), 0);
+        assert_eq!(stores[1].len(), 1);
+        assert_eq!(stores[1].entities(), vec![Entity(2)]);
+    }
+
+    #[test]
+    fn remove_entity_shared_links() {
+        let mut type_store = TypeComponentStore::default();
+        type_store.register(Entity(1), 5_u32);
+        type_store.register_shared::<u32>(Entity(2), Entity(1));
+        let mut archetype_store = ArchetypeComponentStore::default();
+        archetype_store.register(Entity(1), 5_u32);
+        archetype_store.register_shared::<u32>(Entity(2), Entity(1));
+        let mut string_store = StringComponentStore::default();
+        string_store.register("size", Entity(1), 5_u32);
+        string_store.register_shared::<u32>("size", Entity(2), Entity(1));
+
+        let mut stores: Vec<Box<dyn ManageEntities>> = vec![
+            Box::new(type_store),
+            Box::new(archetype_store),
+            Box::new(string_store),
+        ];
+
+        for store in &mut stores {
+            store.remove_entity(Entity(1));
+
+            assert!(store.shared_entities().is_empty());
+            assert!(store.entities().is_empty());
+        }
+    }
+
+    #[test]
+    fn minimal_component_store() {
+        #[derive(Default)]
+        struct CountStore(Vec<Entity>);
+
+        impl ComponentStore for CountStore {
+            fn remove_entity(&mut self, entity: Entity) {
+                self.0.retain(|e| *e != entity);
+            }
+
+            #[cfg(not(feature = "no_std"))]
+            fn print_entity(&self, _entity: Entity) {}
+
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+        }
+
+        let mut ecm = EntityComponentManager::new(VecEntityStore::default(), CountStore::default());
+        let entity = ecm.create_entity().build();
+        ecm.component_store_mut().0.push(entity);
+        ecm.reserve(10);
+
+        assert!(!ecm.component_store().is_empty());
+        assert!(ecm.component_store().check_shared(entity).is_ok());
+        ecm.remove_entity(entity);
+        assert!(ecm.component_store().is_empty());
+    }
+
+    #[test]
+    fn build_checked() {
+        let mut ecm: EntityComponentManager<VecEntityStore, StringComponentStore> =
+            EntityComponentManager::default();
+        let source = ecm
+            .create_entity()
+            .components(StringComponentBuilder::new().with("size", 5_u32).build())
+            .build();
+
+        let shared = ecm
+            .create_entity()
+            .components(
+                StringComponentBuilder::new()
+                    .with_shared::<u32>("size", source)
+                    .build(),
+            )
+            .build_checked();
+        assert!(shared.is_ok());
+
+        let dangling = ecm
+            .create_entity()
+            .components(
+                StringComponentBuilder::new()
+                    .with("name", String::from("Button"))
+                    .with_shared::<u32>("sizes", source)
+                    .build(),
+            )
+            .build_checked();
+        assert_eq!(
+            dangling,
+            Err(NotFound::Key((Entity(2), String::from("sizes"))))
+        );
+        assert!(!ecm.contains_entity(Entity(2)));
+        assert!(!ecm.component_store().contains_entity(Entity(2)));
+
+        let mut ecm: EntityComponentManager<VecEntityStore, TypeComponentStore> =
+            EntityComponentManager::default();
+        let dangling = ecm
+            .create_entity()
+            .components(
+                TypeComponentBuilder::new()
+                    .with_shared::<u32>(Entity(5))
+                    .build(),
+            )
+            .build_checked();
+        assert_eq!(dangling, Err(NotFound::Component(TypeId::of::<u32>())));
+    }
 }
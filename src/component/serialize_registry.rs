@@ -0,0 +1,378 @@
+use core::any::{Any, TypeId};
+
+#[cfg(not(feature = "no_std"))]
+use std::collections::HashMap;
+
+#[cfg(feature = "no_std")]
+use hashbrown::HashMap;
+
+#[cfg(feature = "no_std")]
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{string_component_store::MultiComponent, Component, Entity, StringComponentStore};
+use crate::error::NotFound;
+
+type SerializeFn = Box<dyn Fn(&dyn Any) -> Result<Value, NotFound>>;
+type DeserializeFn = Box<dyn Fn(Value) -> Result<Box<dyn Any>, NotFound>>;
+
+/// The `SerializeRegistry` maps component keys to the serializer and deserializer of the
+/// component type stored under the key. It is used to save and load a `StringComponentStore`.
+#[derive(Default)]
+pub struct SerializeRegistry {
+    entries: HashMap<String, (SerializeFn, DeserializeFn)>,
+}
+
+impl SerializeRegistry {
+    /// Creates an new registry with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the component type `C` for the given `key`.
+    pub fn register<C: Component + Serialize + DeserializeOwned>(&mut self, key: &str) {
+        self.entries.insert(
+            key.to_string(),
+            (
+                Box::new(|component| {
+                    let component = component
+                        .downcast_ref::<C>()
+                        .ok_or(NotFound::TypeMismatch(TypeId::of::<C>()))?;
+                    serde_json::to_value(component)
+                        .map_err(|error| NotFound::Unknown(error.to_string()))
+                }),
+                Box::new(|value| {
+                    serde_json::from_value::<C>(value)
+                        .map(|component| Box::new(component) as Box<dyn Any>)
+                        .map_err(|error| NotFound::Unknown(error.to_string()))
+                }),
+            ),
+        );
+    }
+
+    /// Returns `true` if a component type is registered for the given `key`.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Serializes the `component` stored under the given `key`. If no type is registered for
+    /// the key `NotFound::ComponentKey` will be returned. If the component is not of the
+    /// registered type `NotFound::TypeMismatch` will be returned.
+    pub fn serialize(&self, key: &str, component: &dyn Any) -> Result<Value, NotFound> {
+        match self.entries.get(key) {
+            Some((serialize, _)) => serialize(component),
+            None => Err(NotFound::ComponentKey(key.to_string())),
+        }
+    }
+
+    /// Deserializes a component that is stored under the given `key`.
+    pub fn deserialize(&self, key: &str, value: Value) -> Result<Box<dyn Any>, NotFound> {
+        match self.entries.get(key) {
+            Some((_, deserialize)) => deserialize(value),
+            None => Err(NotFound::ComponentKey(key.to_string())),
+        }
+    }
+}
+
+// The `value` of a key with multiple values is an array of the values.
+#[derive(Serialize, Deserialize)]
+struct ComponentEntry {
+    entity: u32,
+    key: String,
+    value: Value,
+    #[serde(default)]
+    multi: bool,
+}
+
+// Merges the `patch` into the `target` like a JSON merge patch. Fields of objects are merged,
+// `null` removes a field and all other values replace the target.
+fn merge_patch(target: &mut Value, patch: Value) {
+    match patch {
+        Value::Object(patch) => {
+            if !target.is_object() {
+                *target = Value::Object(Map::new());
+            }
+
+            if let Value::Object(target) = target {
+                for (key, value) in patch {
+                    if value.is_null() {
+                        target.remove(&key);
+                    } else {
+                        merge_patch(target.entry(key).or_insert(Value::Null), value);
+                    }
+                }
+            }
+        }
+        patch => *target = patch,
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SharedEntry {
+    entity: u32,
+    key: String,
+    source: u32,
+    source_key: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct StoreDocument {
+    components: Vec<ComponentEntry>,
+    shared: Vec<SharedEntry>,
+}
+
+impl StringComponentStore {
+    /// Serializes all components and shared links of the store. Each component key needs a
+    /// registered type in the `registry`, otherwise `NotFound::ComponentKey` will be returned.
+    pub fn serialize_with_registry(&self, registry: &SerializeRegistry) -> Result<Value, NotFound> {
+        let mut document = StoreDocument::default();
+
+        for ((entity, key), component) in &self.components {
+            let (value, multi) = match component.downcast_ref::<MultiComponent>() {
+                Some(multi) => (
+                    Value::Array(
+                        multi
+                            .0
+                            .iter()
+                            .map(|value| registry.serialize(key, value.as_ref()))
+                            .collect::<Result<_, _>>()?,
+                    ),
+                    true,
+                ),
+                None => (registry.serialize(key, component.as_ref())?, false),
+            };
+
+            document.components.push(ComponentEntry {
+                entity: entity.0,
+                key: key.clone(),
+                value,
+                multi,
+            });
+        }
+
+        for ((entity, key), (source, source_key)) in &self.shared {
+            document.shared.push(SharedEntry {
+                entity: entity.0,
+                key: key.clone(),
+                source: source.0,
+                source_key: source_key.clone(),
+            });
+        }
+
+        document
+            .components
+            .sort_by(|a, b| (a.entity, &a.key).cmp(&(b.entity, &b.key)));
+        document
+            .shared
+            .sort_by(|a, b| (a.entity, &a.key).cmp(&(b.entity, &b.key)));
+
+        serde_json::to_value(document).map_err(|error| NotFound::Unknown(error.to_string()))
+    }
+
+    /// Patches the component of `key` of the given `entity` with the fields of `patch`. The
+    /// component is serialized with the `registry`, the `patch` is merged into it like a JSON
+    /// merge patch and the result replaces the component. Shared components are patched at their
+    /// source. If no type is registered for the key `NotFound::ComponentKey` will be returned and
+    /// if the entity doesn't have the key `NotFound::Key`.
+    pub fn apply_patch(
+        &mut self,
+        key: &str,
+        entity: Entity,
+        patch: Value,
+        registry: &SerializeRegistry,
+    ) -> Result<(), NotFound> {
+        if !registry.contains_key(key) {
+            return Err(NotFound::ComponentKey(key.to_string()));
+        }
+
+        let component = self.component_box_mut(key, entity)?;
+        let mut value = registry.serialize(key, component.as_ref())?;
+        merge_patch(&mut value, patch);
+        *component = registry.deserialize(key, value)?;
+
+        Ok(())
+    }
+
+    /// Creates a store from a `value` created by `serialize_with_registry`. Each component key
+    /// needs a registered type in the `registry`, otherwise `NotFound::ComponentKey` will be
+    /// returned.
+    pub fn deserialize_with_registry(
+        value: Value,
+        registry: &SerializeRegistry,
+    ) -> Result<Self, NotFound> {
+        let document: StoreDocument =
+            serde_json::from_value(value).map_err(|error| NotFound::Unknown(error.to_string()))?;
+        let mut store = StringComponentStore::default();
+
+        for ComponentEntry {
+            entity,
+            key,
+            value,
+            multi,
+        } in document.components
+        {
+            let component = match (multi, value) {
+                (true, Value::Array(values)) => Box::new(MultiComponent(
+                    values
+                        .into_iter()
+                        .map(|value| registry.deserialize(&key, value))
+                        .collect::<Result<_, _>>()?,
+                )),
+                (true, _) => {
+                    return Err(NotFound::Unknown(format!(
+                        "values of key '{}' are not an array",
+                        key
+                    )))
+                }
+                (false, value) => registry.deserialize(&key, value)?,
+            };
+            store.components.insert((Entity(entity), key), component);
+        }
+
+        for entry in document.shared {
+            store.shared.insert(
+                (Entity(entry.entity), entry.key),
+                (Entity(entry.source), entry.source_key),
+            );
+        }
+
+        Ok(store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn round_trip() {
+        let mut registry = SerializeRegistry::new();
+        registry.register::<String>("name");
+        registry.register::<u32>("depth");
+
+        let mut store = StringComponentStore::default();
+        store.register("name", Entity(0), String::from("Button"));
+        store.register("depth", Entity(0), 4_u32);
+        store.register("name", Entity(1), String::from("CheckBox"));
+        store.register_shared::<u32>("depth", Entity(1), Entity(0));
+
+        let json = store
+            .serialize_with_registry(&registry)
+            .unwrap()
+            .to_string();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        let restored = StringComponentStore::deserialize_with_registry(value, &registry).unwrap();
+
+        assert_eq!(restored.get::<String>("name", Entity(0)).unwrap(), "Button");
+        assert_eq!(
+            restored.get::<String>("name", Entity(1)).unwrap(),
+            "CheckBox"
+        );
+        assert_eq!(*restored.get::<u32>("depth", Entity(1)).unwrap(), 4);
+        assert!(!restored.is_origin::<u32>("depth", Entity(1)));
+    }
+
+    #[test]
+    fn round_trip_multi() {
+        let mut registry = SerializeRegistry::new();
+        registry.register::<String>("class");
+        registry.register::<Vec<u32>>("path");
+
+        let mut store = StringComponentStore::default();
+        store.register_multi("class", Entity(0), String::from("primary"));
+        store.register_multi("class", Entity(0), String::from("large"));
+        store.register("path", Entity(0), vec![1_u32, 2]);
+
+        let value = store.serialize_with_registry(&registry).unwrap();
+        let restored = StringComponentStore::deserialize_with_registry(value, &registry).unwrap();
+
+        assert_eq!(
+            restored.get_all::<String>("class", Entity(0)),
+            vec!["primary", "large"]
+        );
+        assert_eq!(restored.get::<Vec<u32>>("path", Entity(0)), Ok(&vec![1, 2]));
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let mut registry = SerializeRegistry::new();
+        registry.register::<u32>("name");
+
+        assert_eq!(
+            registry.serialize("name", &String::from("Button")),
+            Err(NotFound::TypeMismatch(TypeId::of::<u32>()))
+        );
+
+        let mut store = StringComponentStore::default();
+        store.register("name", Entity(0), String::from("Button"));
+        assert_eq!(
+            store.serialize_with_registry(&registry),
+            Err(NotFound::TypeMismatch(TypeId::of::<u32>()))
+        );
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Label {
+        text: String,
+        size: u32,
+    }
+
+    #[test]
+    fn apply_patch() {
+        let mut registry = SerializeRegistry::new();
+        registry.register::<Label>("label");
+        registry.register::<u32>("depth");
+
+        let mut store = StringComponentStore::default();
+        let label = Label {
+            text: String::from("Ok"),
+            size: 12,
+        };
+        store.register("label", Entity(0), label);
+        store.register_shared::<Label>("label", Entity(1), Entity(0));
+
+        store
+            .apply_patch("label", Entity(1), json!({ "size": 16 }), &registry)
+            .unwrap();
+
+        assert_eq!(
+            store.get::<Label>("label", Entity(0)),
+            Ok(&Label {
+                text: String::from("Ok"),
+                size: 16
+            })
+        );
+        assert_eq!(store.version(Entity(0)), 1);
+        assert_eq!(
+            store.apply_patch("name", Entity(0), json!("Button"), &registry),
+            Err(NotFound::ComponentKey(String::from("name")))
+        );
+        assert_eq!(
+            store.apply_patch("depth", Entity(0), json!(2), &registry),
+            Err(NotFound::Key((Entity(0), String::from("depth"))))
+        );
+        assert!(store
+            .apply_patch("label", Entity(0), json!({ "size": "large" }), &registry)
+            .is_err());
+        assert_eq!(store.get::<Label>("label", Entity(1)).unwrap().size, 16);
+    }
+
+    #[test]
+    fn unknown_key() {
+        let registry = SerializeRegistry::new();
+        let mut store = StringComponentStore::default();
+        store.register("name", Entity(0), String::from("Button"));
+
+        assert_eq!(
+            store.serialize_with_registry(&registry),
+            Err(NotFound::ComponentKey(String::from("name")))
+        );
+    }
+}
@@ -1,8 +1,26 @@
-use core::any::Any;
+use core::{
+    any::{Any, TypeId},
+    borrow,
+    hash::{Hash, Hasher},
+};
 
+#[cfg(not(feature = "no_std"))]
 use std::collections::HashMap;
 
-use super::{Component, ComponentBox, ComponentStore, Entity, SharedComponentBox};
+#[cfg(feature = "no_std")]
+use hashbrown::HashMap;
+
+#[cfg(feature = "no_std")]
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use super::{
+    AppendComponents, CloneComponentStore, CloneRegistry, Component, ComponentBox, ComponentStore,
+    DefaultRegistry, Entity, ManageEntities, SharedComponentBox, TypeNameRegistry,
+};
 use crate::error::NotFound;
 
 type BuildComponents = HashMap<String, Box<dyn Any>>;
@@ -10,6 +28,92 @@ type BuildSharedComponents = HashMap<String, (Entity, String)>;
 type Components = HashMap<(Entity, String), Box<dyn Any>>;
 type SharedComponents = HashMap<(Entity, String), (Entity, String)>;
 
+// Borrowed form of a `(Entity, String)` map key, so components could be looked up by
+// `(Entity, &str)` without allocating the key.
+trait KeyRef {
+    fn key(&self) -> (Entity, &str);
+}
+
+impl KeyRef for (Entity, String) {
+    fn key(&self) -> (Entity, &str) {
+        (self.0, self.1.as_str())
+    }
+}
+
+impl KeyRef for (Entity, &str) {
+    fn key(&self) -> (Entity, &str) {
+        *self
+    }
+}
+
+impl<'a> borrow::Borrow<dyn KeyRef + 'a> for (Entity, String) {
+    fn borrow(&self) -> &(dyn KeyRef + 'a) {
+        self
+    }
+}
+
+// Hashes like `(Entity, String)`, so both key forms find the same entry.
+impl Hash for dyn KeyRef + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl PartialEq for dyn KeyRef + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for dyn KeyRef + '_ {}
+
+// Holds all values registered with `register_multi` under one key.
+pub(super) struct MultiComponent(pub(super) Vec<Box<dyn Any>>);
+
+// Downcasts a stored component. A multi component resolves to its first value.
+fn downcast_ref<C: Component>(component: &dyn Any) -> Option<&C> {
+    match component.downcast_ref::<MultiComponent>() {
+        Some(multi) => multi.0.first().and_then(|c| c.downcast_ref()),
+        None => component.downcast_ref(),
+    }
+}
+
+// Downcasts a stored component mutable. A multi component resolves to its first value.
+fn downcast_mut<C: Component>(component: &mut dyn Any) -> Option<&mut C> {
+    if component.is::<MultiComponent>() {
+        return component
+            .downcast_mut::<MultiComponent>()
+            .and_then(|multi| multi.0.first_mut())
+            .and_then(|c| c.downcast_mut());
+    }
+
+    component.downcast_mut()
+}
+
+// Returns the first value of a multi component, so callers never see `MultiComponent`.
+fn first_value(component: &Box<dyn Any>) -> Option<&Box<dyn Any>> {
+    match component.downcast_ref::<MultiComponent>() {
+        Some(multi) => multi.0.first(),
+        None => Some(component),
+    }
+}
+
+// Takes the first value of a multi component, so callers never see `MultiComponent`.
+fn take_first_value(component: Box<dyn Any>) -> Option<Box<dyn Any>> {
+    match component.downcast::<MultiComponent>() {
+        Ok(multi) => multi.0.into_iter().next(),
+        Err(component) => Some(component),
+    }
+}
+
+// Keeps a `NotFound::CyclicShare` error, other errors are replaced by `other`.
+fn keep_cyclic(error: NotFound, other: NotFound) -> NotFound {
+    match error {
+        NotFound::CyclicShare(_) => error,
+        _ => other,
+    }
+}
+
 /// The `StringComponentBuilder` is used to build a set of string key based components.
 #[derive(Default)]
 pub struct StringComponentBuilder {
@@ -45,6 +149,15 @@ impl StringComponentBuilder {
         self
     }
 
+    /// Adds all given key and component pairs to the entity.
+    pub fn with_many(
+        mut self,
+        components: impl IntoIterator<Item = (String, Box<dyn Any>)>,
+    ) -> Self {
+        self.components.extend(components);
+        self
+    }
+
     /// Finishing the creation of the entity.
     pub fn build(self) -> (BuildComponents, BuildSharedComponents) {
         (self.components, self.shared)
@@ -55,50 +168,70 @@ impl StringComponentBuilder {
 /// borrow the components of the entities.
 #[derive(Default, Debug)]
 pub struct StringComponentStore {
-    components: Components,
-    shared: SharedComponents,
+    pub(crate) components: Components,
+    pub(crate) shared: SharedComponents,
+    versions: HashMap<Entity, u64>,
 }
 
-impl ComponentStore for StringComponentStore {
+impl AppendComponents for StringComponentStore {
     type Components = (BuildComponents, BuildSharedComponents);
 
     fn append(&mut self, entity: Entity, components: Self::Components) {
         for (key, value) in components.0 {
+            self.shared.remove(&(entity, key.clone()));
             self.components.insert((entity, key), value);
         }
         for (key, value) in components.1 {
+            self.components.remove(&(entity, key.clone()));
             self.shared.insert((entity, key), (value.0, value.1));
         }
     }
+}
 
-    fn remove_entity(&mut self, entity: impl Into<Entity>) {
-        let entity = entity.into();
-        let keys: Vec<(Entity, String)> = self
+impl ComponentStore for StringComponentStore {
+    // Keys are removed in sorted order so components are dropped deterministically.
+    fn remove_entity(&mut self, entity: Entity) {
+        let mut keys: Vec<(Entity, String)> = self
             .components
             .iter()
             .filter(|&(k, _)| k.0 == entity)
             .map(|(k, _)| k.clone())
             .collect();
+        keys.sort();
 
         for k in keys {
             self.components.remove(&k);
         }
 
-        let keys: Vec<(Entity, String)> = self
-            .shared
-            .iter()
-            .filter(|&(k, _)| k.0 == entity)
-            .map(|(k, _)| k.clone())
+        self.shared
+            .retain(|k, source| k.0 != entity && source.0 != entity);
+        self.versions.remove(&entity);
+    }
+
+    fn remove_entities(&mut self, entities: &[Entity]) {
+        let mut entities = entities.to_vec();
+        entities.sort();
+        let removed = |entity: Entity| entities.binary_search(&entity).is_ok();
+
+        let mut keys: Vec<(Entity, String)> = self
+            .components
+            .keys()
+            .filter(|k| removed(k.0))
+            .cloned()
             .collect();
+        keys.sort();
 
         for k in keys {
-            self.shared.remove(&k);
+            self.components.remove(&k);
         }
-    }
 
-    fn print_entity(&self, entity: impl Into<Entity>) {
-        let entity = entity.into();
+        self.shared
+            .retain(|k, source| !removed(k.0) && !removed(source.0));
+        self.versions.retain(|entity, _| !removed(*entity));
+    }
 
+    #[cfg(not(feature = "no_std"))]
+    fn print_entity(&self, entity: Entity) {
         println!("Components of entity: {}", entity.0);
         for (k, v) in self.components.iter().filter(|&(k, _)| k.0 == entity) {
             println!("Key: {:?}, Value: {:?}", k, v);
@@ -109,13 +242,191 @@ impl ComponentStore for StringComponentStore {
             println!("Key: {:?}, Value: {:?}", k, v);
         }
     }
+
+    fn describe_entity(&self, entity: Entity, _type_names: &TypeNameRegistry) -> String {
+        let mut owned: Vec<String> = self
+            .components
+            .keys()
+            .filter(|k| k.0 == entity)
+            .map(|k| format!("    owns {}\n", k.1))
+            .collect();
+        owned.sort();
+
+        let mut shared: Vec<String> = self
+            .shared
+            .iter()
+            .filter(|(k, _)| k.0 == entity)
+            .map(|(k, (source, source_key))| {
+                format!("    shares {} from {:?}.{}\n", k.1, source, source_key)
+            })
+            .collect();
+        shared.sort();
+
+        owned.concat() + &shared.concat()
+    }
+
+    fn check_shared(&self, entity: Entity) -> Result<(), NotFound> {
+        let mut keys: Vec<&String> = self
+            .shared
+            .keys()
+            .filter(|k| k.0 == entity)
+            .map(|k| &k.1)
+            .collect();
+        keys.sort();
+
+        for key in keys {
+            self.source(entity, key.as_str())
+                .map_err(|error| keep_cyclic(error, NotFound::Key((entity, key.clone()))))?;
+        }
+
+        Ok(())
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.components.reserve(additional);
+    }
+
+    fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+}
+
+impl ManageEntities for StringComponentStore {
+    fn swap_entities(&mut self, a: Entity, b: Entity) {
+        if a == b {
+            return;
+        }
+
+        let swap = |entity: Entity| match entity {
+            e if e == a => b,
+            e if e == b => a,
+            e => e,
+        };
+
+        let keys: Vec<(Entity, String)> = self
+            .components
+            .keys()
+            .filter(|k| k.0 == a || k.0 == b)
+            .cloned()
+            .collect();
+        let components: Vec<_> = keys
+            .into_iter()
+            .filter_map(|k| self.components.remove(&k).map(|c| (k, c)))
+            .collect();
+        for ((entity, key), component) in components {
+            self.components.insert((swap(entity), key), component);
+        }
+
+        let versions = (self.versions.remove(&a), self.versions.remove(&b));
+        if let Some(version) = versions.0 {
+            self.versions.insert(b, version);
+        }
+        if let Some(version) = versions.1 {
+            self.versions.insert(a, version);
+        }
+
+        let keys: Vec<(Entity, String)> = self
+            .shared
+            .iter()
+            .filter(|(k, s)| k.0 == a || k.0 == b || s.0 == a || s.0 == b)
+            .map(|(k, _)| k.clone())
+            .collect();
+        let shared: Vec<_> = keys
+            .into_iter()
+            .filter_map(|k| self.shared.remove(&k).map(|s| (k, s)))
+            .collect();
+        for ((entity, key), (source, source_key)) in shared {
+            self.shared
+                .insert((swap(entity), key), (swap(source), source_key));
+        }
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = self
+            .components
+            .keys()
+            .chain(self.shared.keys())
+            .map(|k| k.0)
+            .collect();
+        entities.sort();
+        entities.dedup();
+        entities
+    }
+
+    fn shared_entities(&self) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = self.shared.keys().map(|k| k.0).collect();
+        entities.sort();
+        entities.dedup();
+        entities
+    }
+
+    fn clear(&mut self) {
+        self.components.clear();
+        self.shared.clear();
+        self.versions.clear();
+    }
+}
+
+impl CloneComponentStore for StringComponentStore {
+    fn clone_with_registry(&self, registry: &CloneRegistry) -> Result<Self, NotFound> {
+        let mut components = HashMap::with_capacity(self.components.len());
+        for (key, component) in &self.components {
+            let copy = match component.downcast_ref::<MultiComponent>() {
+                Some(multi) => Box::new(MultiComponent(
+                    multi
+                        .0
+                        .iter()
+                        .map(|value| registry.clone_component(value.as_ref()))
+                        .collect::<Result<_, _>>()?,
+                )),
+                None => registry.clone_component(component.as_ref())?,
+            };
+            components.insert(key.clone(), copy);
+        }
+
+        Ok(StringComponentStore {
+            components,
+            shared: self.shared.clone(),
+            versions: self.versions.clone(),
+        })
+    }
 }
 
 impl StringComponentStore {
-    /// Register a `component` for the given `entity`.
+    /// Register a `component` for the given `entity`. A shared link of the key is replaced.
     pub fn register<C: Component>(&mut self, key: impl Into<String>, entity: Entity, component: C) {
+        let key = (entity, key.into());
+        self.shared.remove(&key);
+        self.components.insert(key, Box::new(component));
+    }
+
+    /// Adds a `component` to the values of the given `key`. A key can hold multiple values this
+    /// way, which can be read with `get_all`. `get` and `get_mut` return the first value of the
+    /// key. A single component already registered for the key becomes its first value.
+    pub fn register_multi<C: Component>(
+        &mut self,
+        key: impl Into<String>,
+        entity: Entity,
+        component: C,
+    ) {
+        let key = (entity, key.into());
+        self.shared.remove(&key);
+
+        let mut values = match self.components.remove(&key) {
+            Some(existing) => match existing.downcast::<MultiComponent>() {
+                Ok(multi) => multi.0,
+                Err(single) => vec![single],
+            },
+            None => vec![],
+        };
+        values.push(Box::new(component));
+
         self.components
-            .insert((entity, key.into()), Box::new(component));
+            .insert(key, Box::new(MultiComponent(values)));
     }
 
     /// Registers a sharing of the given component between the given entities. Uses as source key the component key.
@@ -123,6 +434,48 @@ impl StringComponentStore {
         self.register_shared_by_source_key::<C>(key, key, target, source);
     }
 
+    /// Registers a sharing of the component under `key` of the `source` with all `targets`. The
+    /// chain of shared links of the source is resolved once and the targets point directly to
+    /// the entity that owns the component. A target that owns the resolved component is skipped.
+    pub fn register_shared_many<C: Component>(
+        &mut self,
+        key: &str,
+        source: Entity,
+        targets: &[Entity],
+    ) {
+        let origin = self
+            .source(source, key)
+            .unwrap_or_else(|_| (source, key.to_string()));
+
+        for target in targets {
+            let target_key = (*target, key.to_string());
+            if target_key == origin {
+                continue;
+            }
+
+            self.components.remove(&target_key);
+            self.shared.insert(target_key, origin.clone());
+        }
+    }
+
+    /// Registers a sharing of the component under `key` like `register_shared`, but keeps a
+    /// component the `target` owns. If the target owns a component under `key`
+    /// `NotFound::OwnedComponent` will be returned and nothing is changed.
+    pub fn register_shared_keep_local<C: Component>(
+        &mut self,
+        key: &str,
+        target: Entity,
+        source: Entity,
+    ) -> Result<(), NotFound> {
+        let target_key = (target, key.to_string());
+        if self.components.contains_key(&target_key) {
+            return Err(NotFound::OwnedComponent(target));
+        }
+
+        self.shared.insert(target_key, (source, key.to_string()));
+        Ok(())
+    }
+
     /// Registers a sharing of the given component between the given entities.
     pub fn register_shared_by_source_key<C: Component>(
         &mut self,
@@ -159,7 +512,96 @@ impl StringComponentStore {
     /// Register a `component_box` for the given `entity`.
     pub fn register_box(&mut self, key: &str, entity: Entity, component_box: ComponentBox) {
         let (_, component) = component_box.consume();
-        self.components.insert((entity, key.into()), component);
+        let key = (entity, key.to_string());
+        self.shared.remove(&key);
+        self.components.insert(key, component);
+    }
+
+    /// Renames the component key `old` to `new` for all entities. Shared links are renamed too.
+    /// A component that is already registered under `new` is replaced.
+    pub fn rename_key(&mut self, old: &str, new: &str) {
+        if old == new {
+            return;
+        }
+
+        let keys: Vec<(Entity, String)> = self
+            .components
+            .keys()
+            .filter(|k| k.1 == old)
+            .cloned()
+            .collect();
+        for key in keys {
+            if let Some(component) = self.components.remove(&key) {
+                self.shared.remove(&(key.0, new.to_string()));
+                self.components.insert((key.0, new.to_string()), component);
+            }
+        }
+
+        let keys: Vec<(Entity, String)> =
+            self.shared.keys().filter(|k| k.1 == old).cloned().collect();
+        for key in keys {
+            if let Some(source) = self.shared.remove(&key) {
+                self.components.remove(&(key.0, new.to_string()));
+                self.shared.insert((key.0, new.to_string()), source);
+            }
+        }
+
+        for source in self.shared.values_mut() {
+            if source.1 == old {
+                source.1 = new.to_string();
+            }
+        }
+    }
+
+    /// Renames the component key `old` to `new` like `rename_key`, but fails with
+    /// `NotFound::KeyExists` without changing anything if an entity has both keys.
+    pub fn rename_key_checked(&mut self, old: &str, new: &str) -> Result<(), NotFound> {
+        let has_key = |entity: Entity, key: &str| {
+            let key = (entity, key.to_string());
+            self.components.contains_key(&key) || self.shared.contains_key(&key)
+        };
+
+        if old != new {
+            if let Some(entity) = self
+                .components
+                .keys()
+                .chain(self.shared.keys())
+                .filter(|k| k.1 == old)
+                .map(|k| k.0)
+                .find(|entity| has_key(*entity, new))
+            {
+                return Err(NotFound::KeyExists((entity, new.to_string())));
+            }
+        }
+
+        self.rename_key(old, new);
+        Ok(())
+    }
+
+    /// Removes the component with the given `key` from the `entity` and returns it. If the
+    /// component is shared by the entity, only the shared link is removed. All shared links
+    /// that point to the removed component are removed too. A key with multiple values returns
+    /// its first value like `get`.
+    pub fn remove(&mut self, key: &str, entity: Entity) -> Option<Box<dyn Any>> {
+        let key = (entity, key.to_string());
+        self.shared.remove(&key);
+
+        let component = self.components.remove(&key);
+
+        if component.is_some() {
+            let keys: Vec<(Entity, String)> = self
+                .shared
+                .iter()
+                .filter(|&(_, s)| *s == key)
+                .map(|(k, _)| k.clone())
+                .collect();
+
+            for k in keys {
+                self.shared.remove(&k);
+            }
+        }
+
+        component.and_then(take_first_value)
     }
 
     /// Returns the number of components in the store.
@@ -172,16 +614,86 @@ impl StringComponentStore {
         self.components.is_empty()
     }
 
+    /// Returns the share of allocated but unused capacity of the inner maps, from `0.0` (no
+    /// waste) to `1.0`. Use `shrink_to_fit` to release the unused capacity.
+    pub fn fragmentation(&self) -> f32 {
+        let len = self.components.len() + self.shared.len();
+        let capacity = self.components.capacity() + self.shared.capacity();
+
+        if capacity == 0 {
+            return 0.0;
+        }
+
+        1.0 - len as f32 / capacity as f32
+    }
+
+    /// Shrinks the capacity of the inner maps as much as possible.
+    pub fn shrink_to_fit(&mut self) {
+        self.components.shrink_to_fit();
+        self.shared.shrink_to_fit();
+    }
+
     /// Returns `true` if the store contains the specific entity.
     pub fn contains_entity(&self, entity: Entity) -> bool {
         self.components.iter().any(|(k, _)| k.0 == entity)
     }
 
+    /// Returns the sorted keys of all components owned by the given `entity`.
+    pub fn keys_of(&self, entity: Entity) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .components
+            .keys()
+            .filter(|k| k.0 == entity)
+            .map(|k| k.1.clone())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Returns the sorted keys of all components the given `entity` shares from other entities.
+    pub fn shared_keys_of(&self, entity: Entity) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .shared
+            .keys()
+            .filter(|k| k.0 == entity)
+            .map(|k| k.1.clone())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Returns an iterator over the keys and components owned by the given `entity` whose key
+    /// starts with `prefix`. A key with multiple values returns its first value like `get`.
+    pub fn components_with_prefix<'a>(
+        &'a self,
+        entity: Entity,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a String, &'a Box<dyn Any>)> + 'a {
+        self.components
+            .iter()
+            .filter(move |(k, _)| k.0 == entity && k.1.starts_with(prefix))
+            .filter_map(|(k, c)| first_value(c).map(|c| (&k.1, c)))
+    }
+
     /// Returns `true` if entity is the origin of the requested component `false`.
     pub fn is_origin<C: Component>(&self, key: &str, entity: Entity) -> bool {
         self.components.contains_key(&(entity, key.to_string()))
     }
 
+    /// Points every shared link directly at the entity that owns the component, so chains of
+    /// shared links are resolved in one step by `get`. Links without an owner are kept.
+    pub fn deduplicate_shared(&mut self) {
+        let origins: Vec<((Entity, String), (Entity, String))> = self
+            .shared
+            .keys()
+            .filter_map(|k| self.source(k.0, k.1.as_str()).ok().map(|s| (k.clone(), s)))
+            .collect();
+
+        for (key, origin) in origins {
+            self.shared.insert(key, origin);
+        }
+    }
+
     // Search the the source in the entity map.
     fn source_from_shared(
         &self,
@@ -191,17 +703,19 @@ impl StringComponentStore {
         let key = key.into();
         self.shared
             .get(&(entity, key.clone()))
-            .ok_or_else(|| NotFound::Key((entity, key)))
-            .map(|s| s.clone())
+            .ok_or(NotFound::Key((entity, key)))
+            .cloned()
     }
 
     // Returns the source. First search in entities map. If not found search in shared entity map.
+    // Shared links that form a cycle return `NotFound::CyclicShare`.
     fn source(&self, entity: Entity, key: impl Into<String>) -> Result<(Entity, String), NotFound> {
         let key = (entity, key.into());
         if !self.components.contains_key(&key) {
             let mut source = self.source_from_shared(key.1.clone(), key.0);
 
-            loop {
+            // each shared link is followed at most once, so more steps mean a cycle
+            for _ in 0..self.shared.len() {
                 if source.is_err() || self.components.contains_key(source.as_ref().unwrap()) {
                     return source;
                 }
@@ -211,13 +725,35 @@ impl StringComponentStore {
                     source.as_ref().unwrap().0,
                 );
             }
+
+            return Err(NotFound::CyclicShare(key));
         }
 
         Result::Ok(key)
     }
 
+    /// Returns references of all values of type `C` registered for the `key` of the given
+    /// `entity`. A key with a single component returns this component. If the entity doesn't
+    /// have the key an empty vector will be returned.
+    pub fn get_all<C: Component>(&self, key: &str, entity: Entity) -> Vec<&C> {
+        let component = self
+            .source(entity, key)
+            .ok()
+            .and_then(|source| self.components.get(&source));
+
+        match component {
+            Some(component) => match component.downcast_ref::<MultiComponent>() {
+                Some(multi) => multi.0.iter().filter_map(|c| c.downcast_ref()).collect(),
+                None => component.downcast_ref().into_iter().collect(),
+            },
+            None => vec![],
+        }
+    }
+
     /// Returns a reference of a component of type `C` from the given `entity`. If the entity does
-    /// not exists or it doesn't have a component of type `C` `NotFound` will be returned.
+    /// not exists or it doesn't have a component of type `C` `NotFound` will be returned. If the
+    /// stored component is not of type `C` `NotFound::TypeMismatch` will be returned. Shared
+    /// links that form a cycle return `NotFound::CyclicShare`.
     pub fn get<C: Component>(&self, key: &str, entity: Entity) -> Result<&C, NotFound> {
         let source = self.source(entity, key);
 
@@ -225,39 +761,152 @@ impl StringComponentStore {
             Ok(source) => self
                 .components
                 .get(&(source.0, source.1))
-                .ok_or_else(|| NotFound::Entity(entity))
-                .map(|component| {
-                    component
-                        .downcast_ref()
-                        .expect("StringComponentStore.get: internal downcast error")
+                .ok_or(NotFound::Entity(entity))
+                .and_then(|component| {
+                    downcast_ref(component.as_ref())
+                        .ok_or(NotFound::TypeMismatch(TypeId::of::<C>()))
                 }),
-            Err(_) => Result::Err(NotFound::Entity(entity)),
+            Err(error) => Result::Err(keep_cyclic(error, NotFound::Entity(entity))),
         }
     }
 
+    /// Returns a reference of a component of type `C` from the given `entity`. If the entity
+    /// has no component for `key`, also if its shared source is missing, the default of the
+    /// `registry` is registered for it first and replaces the shared link. The default of the key
+    /// is used before the default of the type `C`. If no default is registered `NotFound::Key` will be returned.
+    pub fn get_or_default<C: Component>(
+        &mut self,
+        key: &str,
+        entity: Entity,
+        registry: &DefaultRegistry,
+    ) -> Result<&C, NotFound> {
+        if self.source(entity, key).is_err() {
+            let component = registry
+                .default_for_key(key)
+                .or_else(|_| registry.default_component(TypeId::of::<C>()))
+                .map_err(|_| NotFound::Key((entity, key.into())))?;
+            self.shared.remove(&(entity, key.to_string()));
+            self.components.insert((entity, key.into()), component);
+        }
+
+        self.get::<C>(key, entity)
+    }
+
+    /// Returns the component registered for the `key` of the given `entity` without downcasting
+    /// it. Shared components are resolved and a multi component resolves to its first value.
+    /// Shared links that form a cycle return `NotFound::CyclicShare`.
+    pub fn get_dyn(&self, key: &str, entity: Entity) -> Result<&dyn Any, NotFound> {
+        let source = self
+            .source(entity, key)
+            .map_err(|error| keep_cyclic(error, NotFound::Entity(entity)))?;
+        let component = self
+            .components
+            .get(&source)
+            .ok_or(NotFound::Entity(entity))?;
+
+        match component.downcast_ref::<MultiComponent>() {
+            Some(multi) => multi
+                .0
+                .first()
+                .map(|c| c.as_ref())
+                .ok_or(NotFound::Entity(entity)),
+            None => Ok(component.as_ref()),
+        }
+    }
+
+    /// Returns the components of type `C` registered for the `key` of the given `entities`.
+    /// Entities without the component are skipped.
+    pub fn get_many_for<C: Component>(&self, key: &str, entities: &[Entity]) -> Vec<(Entity, &C)> {
+        entities
+            .iter()
+            .filter_map(|entity| self.get::<C>(key, *entity).ok().map(|c| (*entity, c)))
+            .collect()
+    }
+
+    /// Returns a reference of a component of type `C` the given `entity` owns under `key`.
+    /// Shared components are not resolved and the key is not allocated. Use it in hot loops
+    /// where the entity is known to own the component, otherwise it panics.
+    pub fn get_unchecked<C: Component>(&self, key: &str, entity: Entity) -> &C {
+        let component = self.components.get(&(entity, key) as &dyn KeyRef);
+        debug_assert!(
+            component.is_some(),
+            "StringComponentStore.get_unchecked: {:?} does not own the key {}",
+            entity,
+            key
+        );
+
+        component
+            .and_then(|component| downcast_ref(component.as_ref()))
+            .expect("StringComponentStore.get_unchecked: component not found")
+    }
+
     /// Returns a mutable reference of a component of type `C` from the given `entity`. If the entity does
-    /// not exists or it doesn't have a component of type `C` `NotFound` will be returned.
+    /// not exists or it doesn't have a component of type `C` `NotFound` will be returned. If the
+    /// stored component is not of type `C` `NotFound::TypeMismatch` will be returned. Shared
+    /// links that form a cycle return `NotFound::CyclicShare`. The version of the entity that
+    /// owns the component is increased.
     pub fn get_mut<C: Component>(&mut self, key: &str, entity: Entity) -> Result<&mut C, NotFound> {
-        let source = self.source(entity, key);
+        let source = self
+            .source(entity, key)
+            .map_err(|error| keep_cyclic(error, NotFound::Entity(entity)))?;
 
-        match source {
-            Ok(source) => self
-                .components
-                .get_mut(&(source.0, source.1))
-                .ok_or_else(|| NotFound::Entity(entity))
-                .map(|component| {
-                    component
-                        .downcast_mut()
-                        .expect("StringComponentStore.get_mut: internal downcast error")
-                }),
-            Err(_) => Result::Err(NotFound::Entity(entity)),
+        let component = self
+            .components
+            .get_mut(&source)
+            .ok_or(NotFound::Entity(entity))?;
+        let component =
+            downcast_mut(component.as_mut()).ok_or(NotFound::TypeMismatch(TypeId::of::<C>()))?;
+
+        *self.versions.entry(source.0).or_insert(0) += 1;
+        Ok(component)
+    }
+
+    // Returns the stored box of the component of `key` resolved like `get_mut`, so it could be
+    // replaced. A multi component resolves to its first value. The version of the entity that
+    // owns the component is increased.
+    #[cfg(feature = "serde")]
+    pub(super) fn component_box_mut(
+        &mut self,
+        key: &str,
+        entity: Entity,
+    ) -> Result<&mut Box<dyn Any>, NotFound> {
+        let not_found = || NotFound::Key((entity, key.to_string()));
+        let source = self
+            .source(entity, key)
+            .map_err(|error| keep_cyclic(error, not_found()))?;
+        let component = self.components.get_mut(&source).ok_or_else(not_found)?;
+        *self.versions.entry(source.0).or_insert(0) += 1;
+
+        if component.as_ref().is::<MultiComponent>() {
+            return component
+                .downcast_mut::<MultiComponent>()
+                .and_then(|multi| multi.0.first_mut())
+                .ok_or_else(not_found);
         }
+
+        Ok(component)
+    }
+
+    /// Returns the version of the given `entity`. It is increased each time a component the
+    /// entity owns is borrowed with `get_mut`, so systems could skip entities whose version did
+    /// not change since their last run.
+    pub fn version(&self, entity: Entity) -> u64 {
+        self.versions.get(&entity).copied().unwrap_or(0)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    struct DropRecorder(&'static str, Rc<RefCell<Vec<&'static str>>>);
+
+    impl Drop for DropRecorder {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
 
     #[test]
     fn builder_with() {
@@ -291,6 +940,58 @@ mod tests {
         assert!(!store.contains_entity(entity));
     }
 
+    #[test]
+    fn remove() {
+        let mut store = StringComponentStore::default();
+        let entity = Entity::from(1);
+        store.register("header", entity, String::from("Header"));
+        store.register("content", entity, String::from("Content"));
+
+        let component = store.remove("header", entity);
+
+        assert_eq!(
+            *component.unwrap().downcast::<String>().unwrap(),
+            String::from("Header")
+        );
+        assert!(store.get::<String>("header", entity).is_err());
+        assert_eq!(
+            store.get::<String>("content", entity),
+            Ok(&String::from("Content"))
+        );
+    }
+
+    #[test]
+    fn remove_shared() {
+        let mut store = StringComponentStore::default();
+        let source = Entity::from(1);
+        let target = Entity::from(2);
+        store.register("test", source, String::from("Test"));
+        store.register_shared::<String>("test", target, source);
+
+        assert!(store.remove("test", target).is_none());
+        assert!(store.get::<String>("test", target).is_err());
+        assert!(store.get::<String>("test", source).is_ok());
+
+        store.register_shared::<String>("test", target, source);
+        assert!(store.remove("test", source).is_some());
+        assert!(store.get::<String>("test", target).is_err());
+        assert!(store.shared.is_empty());
+    }
+
+    #[test]
+    fn remove_entity_order() {
+        let mut store = StringComponentStore::default();
+        let entity = Entity::from(1);
+        let dropped = Rc::new(RefCell::new(vec![]));
+
+        for key in &["d", "b", "e", "a", "c"] {
+            store.register(*key, entity, DropRecorder(key, dropped.clone()));
+        }
+        store.remove_entity(entity);
+
+        assert_eq!(*dropped.borrow(), vec!["a", "b", "c", "d", "e"]);
+    }
+
     #[test]
     fn register() {
         let mut store = StringComponentStore::default();
@@ -302,13 +1003,86 @@ mod tests {
         assert!(store.get::<String>("test", entity).is_ok());
     }
 
+    #[test]
+    fn register_over_shared() {
+        let mut store = StringComponentStore::default();
+        store.register("size", Entity(0), 5_u32);
+        store.register_shared::<u32>("size", Entity(1), Entity(0));
+        store.register("size", Entity(1), 7_u32);
+        store.register_shared::<u32>("size", Entity(2), Entity(0));
+        store.register_multi("size", Entity(2), 3_u32);
+        store.register_shared::<u32>("size", Entity(3), Entity(0));
+        store.register_box("size", Entity(3), ComponentBox::new(9_u32));
+
+        *store.get_mut::<u32>("size", Entity(0)).unwrap() = 6;
+
+        assert_eq!(store.get::<u32>("size", Entity(1)), Ok(&7));
+        assert_eq!(store.get::<u32>("size", Entity(2)), Ok(&3));
+        assert_eq!(store.get::<u32>("size", Entity(3)), Ok(&9));
+        assert!(store.shared_entities().is_empty());
+    }
+
+    #[test]
+    fn reserve() {
+        let mut store = StringComponentStore::default();
+        store.reserve(100);
+
+        assert!(store.components.capacity() >= 100);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn register_multi() {
+        let mut store = StringComponentStore::default();
+        let entity = Entity::from(1);
+        let target = Entity::from(2);
+        store.register("listener", entity, String::from("first"));
+        store.register_multi("listener", entity, String::from("second"));
+        store.register_multi("listener", entity, String::from("third"));
+        store.register_shared::<String>("listener", target, entity);
+
+        assert_eq!(
+            store.get_all::<String>("listener", entity),
+            vec!["first", "second", "third"]
+        );
+        assert_eq!(store.get_all::<String>("listener", target).len(), 3);
+        assert_eq!(store.get::<String>("listener", entity).unwrap(), "first");
+
+        store
+            .get_mut::<String>("listener", target)
+            .unwrap()
+            .push('!');
+        assert_eq!(store.get::<String>("listener", entity).unwrap(), "first!");
+        assert!(store.get_all::<String>("unknown", entity).is_empty());
+    }
+
+    #[test]
+    fn fragmentation() {
+        let mut store = StringComponentStore::default();
+        assert_eq!(store.fragmentation(), 0.0);
+
+        for i in 0..1000 {
+            store.register("value", Entity::from(i), i);
+        }
+        for i in 10..1000 {
+            store.remove_entity(Entity::from(i));
+        }
+
+        let fragmentation = store.fragmentation();
+        assert!(fragmentation > 0.5);
+
+        store.shrink_to_fit();
+        assert!(store.fragmentation() < fragmentation);
+        assert_eq!(store.len(), 10);
+    }
+
     #[test]
     fn len() {
         let mut store = StringComponentStore::default();
         let entity = Entity::from(1);
 
         store.register("string", entity, String::from("Test"));
-        store.register("float", entity, 5 as f64);
+        store.register("float", entity, 5_f64);
 
         assert_eq!(store.len(), 2);
     }
@@ -332,4 +1106,390 @@ mod tests {
         assert!(!store.is_origin::<String>("test", target));
         assert!(!store.is_origin::<String>("test", target_next));
     }
+
+    #[test]
+    fn clone_with_registry() {
+        let mut registry = CloneRegistry::new();
+        registry.register::<String>();
+        registry.register::<u32>();
+
+        let mut store = StringComponentStore::default();
+        store.register("name", Entity(0), String::from("Button"));
+        store.register_multi("tags", Entity(0), 1_u32);
+        store.register_multi("tags", Entity(0), 2_u32);
+        store.register_shared::<String>("name", Entity(1), Entity(0));
+
+        let copy = store.clone_with_registry(&registry).unwrap();
+        *store.get_mut::<String>("name", Entity(0)).unwrap() = String::from("CheckBox");
+
+        assert_eq!(copy.get::<String>("name", Entity(0)).unwrap(), "Button");
+        assert_eq!(copy.get::<String>("name", Entity(1)).unwrap(), "Button");
+        assert_eq!(copy.get_all::<u32>("tags", Entity(0)), vec![&1, &2]);
+    }
+
+    #[test]
+    fn register_shared_keep_local() {
+        let mut store = StringComponentStore::default();
+        let source = Entity::from(1);
+        let target = Entity::from(2);
+        store.register("name", source, String::from("Button"));
+        store.register("name", target, String::from("CheckBox"));
+
+        assert_eq!(
+            store.register_shared_keep_local::<String>("name", target, source),
+            Err(NotFound::OwnedComponent(target))
+        );
+        assert_eq!(store.get::<String>("name", target).unwrap(), "CheckBox");
+        assert!(store.is_origin::<String>("name", target));
+    }
+
+    #[test]
+    fn rename_key() {
+        let mut store = StringComponentStore::default();
+        let source = Entity::from(1);
+        let target = Entity::from(2);
+        store.register("text", source, String::from("Button"));
+        store.register_shared::<String>("text", target, source);
+
+        store.rename_key("text", "label");
+
+        assert!(store.get::<String>("text", source).is_err());
+        assert_eq!(store.get::<String>("label", source).unwrap(), "Button");
+        assert_eq!(store.get::<String>("label", target).unwrap(), "Button");
+        assert!(!store.is_origin::<String>("label", target));
+    }
+
+    #[test]
+    fn rename_key_checked() {
+        let mut store = StringComponentStore::default();
+        let entity = Entity::from(1);
+        store.register("text", entity, String::from("Button"));
+        store.register("label", entity, String::from("CheckBox"));
+        store.register("depth", entity, 2_u32);
+
+        assert_eq!(
+            store.rename_key_checked("text", "label"),
+            Err(NotFound::KeyExists((entity, String::from("label"))))
+        );
+        assert_eq!(store.get::<String>("text", entity).unwrap(), "Button");
+        assert_eq!(store.get::<String>("label", entity).unwrap(), "CheckBox");
+
+        assert!(store.rename_key_checked("depth", "z_index").is_ok());
+        assert_eq!(*store.get::<u32>("z_index", entity).unwrap(), 2);
+        assert!(store.get::<u32>("depth", entity).is_err());
+    }
+
+    #[test]
+    fn get_unchecked() {
+        let mut store = StringComponentStore::default();
+        store.register("depth", Entity(1), 4_u32);
+
+        assert_eq!(*store.get_unchecked::<u32>("depth", Entity(1)), 4);
+        for id in 0..100 {
+            store.register(format!("depth{}", id), Entity(id), id);
+        }
+        assert_eq!(*store.get_unchecked::<u32>("depth42", Entity(42)), 42);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "does not own the key")]
+    fn get_unchecked_shared() {
+        let mut store = StringComponentStore::default();
+        store.register("depth", Entity(1), 4_u32);
+        store.register_shared::<u32>("depth", Entity(2), Entity(1));
+
+        store.get_unchecked::<u32>("depth", Entity(2));
+    }
+
+    #[test]
+    fn get_many_for() {
+        let mut store = StringComponentStore::default();
+        store.register("depth", Entity(1), 1_u32);
+        store.register("depth", Entity(2), 2_u32);
+        store.register("depth", Entity(3), 3_u32);
+        store.register_shared::<u32>("depth", Entity(4), Entity(3));
+        store.register("name", Entity(5), String::from("Button"));
+
+        assert_eq!(
+            store.get_many_for::<u32>("depth", &[Entity(2), Entity(4), Entity(5), Entity(6)]),
+            vec![(Entity(2), &2), (Entity(4), &3)]
+        );
+    }
+
+    #[test]
+    fn deduplicate_shared() {
+        let mut store = StringComponentStore::default();
+        store.register("text", Entity(0), String::from("Button"));
+        store.register_shared::<String>("text", Entity(1), Entity(0));
+        store.register_shared_by_source_key::<String>("label", "text", Entity(2), Entity(1));
+        store.register_shared_by_source_key::<String>("title", "label", Entity(3), Entity(2));
+
+        store.deduplicate_shared();
+
+        for (entity, key) in &[(1, "text"), (2, "label"), (3, "title")] {
+            assert_eq!(
+                store.shared.get(&(Entity(*entity), key.to_string())),
+                Some(&(Entity(0), String::from("text")))
+            );
+            assert_eq!(store.get::<String>(key, Entity(*entity)).unwrap(), "Button");
+        }
+    }
+
+    #[test]
+    fn swap_entities() {
+        let mut store = StringComponentStore::default();
+        let a = Entity(1);
+        let b = Entity(2);
+        let other = Entity(3);
+        store.register("name", a, String::from("A"));
+        store.register("name", b, String::from("B"));
+        store.register("depth", a, 1_u32);
+        store.register_shared::<String>("name", other, a);
+        store.register_shared::<u32>("depth", b, a);
+
+        store.swap_entities(a, b);
+
+        assert_eq!(store.get::<String>("name", a).unwrap(), "B");
+        assert_eq!(store.get::<String>("name", b).unwrap(), "A");
+        assert!(store.is_origin::<u32>("depth", b));
+        assert!(!store.is_origin::<u32>("depth", a));
+        assert_eq!(*store.get::<u32>("depth", a).unwrap(), 1);
+        assert_eq!(store.get::<String>("name", other).unwrap(), "A");
+    }
+
+    #[test]
+    fn keys_of() {
+        let mut store = StringComponentStore::default();
+        let entity = Entity::from(1);
+        let other = Entity::from(2);
+        store.register("width", entity, 5_u32);
+        store.register("name", entity, String::from("Button"));
+        store.register("height", entity, 7_u32);
+        store.register("size", other, 2_u32);
+        store.register_shared::<u32>("size", entity, other);
+
+        assert_eq!(
+            store.keys_of(entity),
+            vec![
+                String::from("height"),
+                String::from("name"),
+                String::from("width")
+            ]
+        );
+        assert_eq!(store.shared_keys_of(entity), vec![String::from("size")]);
+        assert!(store.shared_keys_of(other).is_empty());
+    }
+
+    #[test]
+    fn components_with_prefix() {
+        let mut store = StringComponentStore::default();
+        let entity = Entity::from(1);
+        store.register("style.color", entity, String::from("red"));
+        store.register("style.border", entity, 2_u32);
+        store.register("size", entity, 5_u32);
+        store.register("style.color", Entity::from(2), String::from("blue"));
+
+        let mut keys: Vec<&String> = store
+            .components_with_prefix(entity, "style.")
+            .map(|(key, _)| key)
+            .collect();
+        keys.sort();
+
+        assert_eq!(keys, vec!["style.border", "style.color"]);
+        let color = store
+            .components_with_prefix(entity, "style.color")
+            .next()
+            .and_then(|(_, c)| c.downcast_ref::<String>());
+        assert_eq!(color, Some(&String::from("red")));
+        assert_eq!(store.components_with_prefix(entity, "layout.").count(), 0);
+    }
+
+    #[test]
+    fn multi_key_values() {
+        let mut store = StringComponentStore::default();
+        let entity = Entity::from(1);
+        let target = Entity::from(2);
+        store.register_multi("style.listener", entity, String::from("first"));
+        store.register_multi("style.listener", entity, String::from("second"));
+        store.register_shared::<String>("style.listener", target, entity);
+
+        let listener = store
+            .components_with_prefix(entity, "style.")
+            .next()
+            .and_then(|(_, c)| c.downcast_ref::<String>());
+        assert_eq!(listener, Some(&String::from("first")));
+
+        let removed = store.remove("style.listener", entity).unwrap();
+        assert_eq!(
+            removed.downcast_ref::<String>(),
+            Some(&String::from("first"))
+        );
+        assert!(store.remove("style.listener", entity).is_none());
+        assert!(store.get::<String>("style.listener", target).is_err());
+    }
+
+    #[test]
+    fn entities() {
+        let mut store = StringComponentStore::default();
+        assert!(store.entities().is_empty());
+        store.register("size", Entity::from(3), 5_u32);
+        store.register("width", Entity::from(1), 2_f64);
+        store.register("size", Entity::from(1), 3_u32);
+        store.register_shared::<u32>("size", Entity::from(4), Entity::from(3));
+        store.register_shared::<f64>("width", Entity::from(3), Entity::from(1));
+
+        assert_eq!(
+            store.entities(),
+            vec![Entity::from(1), Entity::from(3), Entity::from(4)]
+        );
+    }
+
+    #[test]
+    fn builder_with_many() {
+        let components: Vec<(String, Box<dyn Any>)> = vec![
+            (String::from("width"), Box::new(5_u32)),
+            (String::from("name"), Box::new(String::from("Button"))),
+        ];
+        let mut store = StringComponentStore::default();
+        let entity = Entity::from(1);
+        store.append(
+            entity,
+            StringComponentBuilder::new()
+                .with("height", 2_u32)
+                .with_many(components)
+                .build(),
+        );
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get::<u32>("width", entity), Ok(&5));
+        assert_eq!(
+            store.get::<String>("name", entity),
+            Ok(&String::from("Button"))
+        );
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let mut store = StringComponentStore::default();
+        let entity = Entity::from(1);
+        store.register_box("name", entity, ComponentBox::new(String::from("Button")));
+
+        let mismatch = NotFound::TypeMismatch(TypeId::of::<u32>());
+        assert_eq!(store.get::<u32>("name", entity), Err(mismatch.clone()));
+        assert_eq!(
+            store.get_mut::<u32>("name", entity).map(|c| *c),
+            Err(mismatch)
+        );
+        assert!(store.get::<String>("name", entity).is_ok());
+    }
+
+    #[test]
+    fn version() {
+        let mut store = StringComponentStore::default();
+        let source = Entity::from(1);
+        let target = Entity::from(2);
+        store.register("size", source, 5_u32);
+        store.register_shared::<u32>("size", target, source);
+        assert_eq!(store.version(source), 0);
+
+        store.get::<u32>("size", source).unwrap();
+        assert_eq!(store.version(source), 0);
+
+        *store.get_mut::<u32>("size", source).unwrap() += 1;
+        *store.get_mut::<u32>("size", target).unwrap() += 1;
+        assert!(store.get_mut::<f64>("size", source).is_err());
+
+        assert_eq!(store.version(source), 2);
+        assert_eq!(store.version(target), 0);
+
+        store.remove_entity(source);
+        assert_eq!(store.version(source), 0);
+    }
+
+    #[test]
+    fn get_or_default() {
+        #[derive(Default, Debug, PartialEq)]
+        struct Counter(u32);
+
+        let mut registry = DefaultRegistry::new();
+        registry.register::<Counter>();
+        registry.register_key("clicks", || Counter(10));
+        let mut store = StringComponentStore::default();
+        let entity = Entity::from(1);
+        store.register_shared::<Counter>("counter", entity, Entity::from(2));
+
+        assert_eq!(
+            store.get_or_default::<Counter>("counter", entity, &registry),
+            Ok(&Counter(0))
+        );
+        assert!(store.shared_entities().is_empty());
+        assert!(store.check_shared(entity).is_ok());
+        assert_eq!(
+            store.get_or_default::<Counter>("clicks", entity, &registry),
+            Ok(&Counter(10))
+        );
+        assert_eq!(
+            store.get_or_default::<u32>("size", entity, &registry),
+            Err(NotFound::Key((entity, String::from("size"))))
+        );
+    }
+
+    #[test]
+    fn register_shared_many() {
+        let mut store = StringComponentStore::default();
+        let source = Entity::from(0);
+        let link = Entity::from(1);
+        store.register("theme", source, String::from("dark"));
+        store.register_shared::<String>("theme", link, source);
+        let targets: Vec<Entity> = (2..7).map(Entity::from).collect();
+        store.register("theme", targets[0], String::from("light"));
+
+        store.register_shared_many::<String>("theme", link, &targets);
+        store.register_shared_many::<String>("theme", link, &[source]);
+
+        for target in &targets {
+            assert_eq!(
+                store.get::<String>("theme", *target),
+                Ok(&String::from("dark"))
+            );
+            assert_eq!(
+                store.shared.get(&(*target, String::from("theme"))),
+                Some(&(source, String::from("theme")))
+            );
+        }
+        assert!(store.is_origin::<String>("theme", source));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn cyclic_share() {
+        let mut store = StringComponentStore::default();
+        let a = Entity::from(1);
+        let b = Entity::from(2);
+        store.register("size", a, 5_u32);
+        store.register_shared::<u32>("size", b, a);
+        store.register_shared::<u32>("size", a, b);
+
+        assert_eq!(
+            store.source(a, "size"),
+            Err(NotFound::CyclicShare((a, String::from("size"))))
+        );
+        assert_eq!(
+            store.get::<u32>("size", a),
+            Err(NotFound::CyclicShare((a, String::from("size"))))
+        );
+        assert_eq!(
+            store.get_mut::<u32>("size", b),
+            Err(NotFound::CyclicShare((b, String::from("size"))))
+        );
+        assert_eq!(
+            store.get_dyn("size", a).err(),
+            Some(NotFound::CyclicShare((a, String::from("size"))))
+        );
+        assert_eq!(
+            store.check_shared(a),
+            Err(NotFound::CyclicShare((a, String::from("size"))))
+        );
+    }
 }
@@ -0,0 +1,54 @@
+use core::any::TypeId;
+
+#[cfg(not(feature = "no_std"))]
+use std::collections::HashMap;
+
+#[cfg(feature = "no_std")]
+use hashbrown::HashMap;
+
+use super::Component;
+
+/// The `TypeNameRegistry` maps the type ids of components to readable names. It is used to
+/// describe the components of a `TypeComponentStore`, because a `TypeId` alone has no name.
+#[derive(Default, Debug)]
+pub struct TypeNameRegistry {
+    names: HashMap<TypeId, &'static str>,
+}
+
+impl TypeNameRegistry {
+    /// Creates an new registry with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the component type `C` with its type name.
+    pub fn register<C: Component>(&mut self) {
+        self.register_name::<C>(core::any::type_name::<C>());
+    }
+
+    /// Registers the component type `C` with the given `name`.
+    pub fn register_name<C: Component>(&mut self, name: &'static str) {
+        self.names.insert(TypeId::of::<C>(), name);
+    }
+
+    /// Returns the name of the given `type_id` or `None` if it is not registered.
+    pub fn get(&self, type_id: TypeId) -> Option<&'static str> {
+        self.names.get(&type_id).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register() {
+        let mut registry = TypeNameRegistry::new();
+        registry.register::<u32>();
+        registry.register_name::<String>("Name");
+
+        assert_eq!(registry.get(TypeId::of::<u32>()), Some("u32"));
+        assert_eq!(registry.get(TypeId::of::<String>()), Some("Name"));
+        assert_eq!(registry.get(TypeId::of::<f64>()), None);
+    }
+}
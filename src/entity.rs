@@ -1,7 +1,28 @@
+use core::{iter::Copied, slice::Iter};
+
+#[cfg(feature = "no_std")]
+use alloc::vec::Vec;
+
 /// Represents an entity.
 #[derive(Copy, Clone, PartialEq, Hash, Eq, Debug, Ord, PartialOrd, Default)]
 pub struct Entity(pub u32);
 
+impl Entity {
+    /// Sentinel for an invalid entity. It is distinct from all entities created by a world,
+    /// because ids are counted up from 0.
+    pub const INVALID: Entity = Entity(u32::MAX);
+
+    /// Creates an entity from the given `index`.
+    pub const fn from_index(index: u32) -> Self {
+        Entity(index)
+    }
+
+    /// Returns the index of the entity.
+    pub const fn index(&self) -> u32 {
+        self.0
+    }
+}
+
 impl From<u32> for Entity {
     fn from(u: u32) -> Self {
         Entity(u)
@@ -10,33 +31,114 @@ impl From<u32> for Entity {
 
 /// This trait is used to define a custom store for entities.
 /// A entity container is used for entity iteration inside of the
-/// system's run methods.
-pub trait EntityStore {
+/// system's run methods. The id type of the entities is given by `I`
+/// and defaults to `Entity`.
+///
+/// The `EntityComponentManager` and the `World` only work with stores of
+/// `Entity` ids. Other id types could be used for standalone stores, e.g.
+/// to track 64-bit ids of a network session.
+pub trait EntityStore<I = Entity> {
     /// Registers the give 'entity'.
-    fn register_entity(&mut self, entity: impl Into<Entity>);
+    fn register_entity(&mut self, entity: impl Into<I>);
 
     /// Removes the given 'entity'.
-    fn remove_entity(&mut self, entity: impl Into<Entity>);
+    fn remove_entity(&mut self, entity: impl Into<I>);
+
+    /// Returns all registered entities.
+    fn entities(&self) -> Vec<I>;
+
+    /// Returns `true` if the given `entity` is registered.
+    fn contains(&self, entity: I) -> bool
+    where
+        I: PartialEq,
+    {
+        self.entities().contains(&entity)
+    }
+
+    /// Removes all entities for which `f` returns `false`.
+    fn retain(&mut self, mut f: impl FnMut(&I) -> bool) {
+        for entity in self.entities() {
+            if !f(&entity) {
+                self.remove_entity(entity);
+            }
+        }
+    }
+
+    /// Removes all entities.
+    fn clear(&mut self) {
+        for entity in self.entities() {
+            self.remove_entity(entity);
+        }
+    }
+
+    /// Returns the number of registered entities.
+    fn len(&self) -> usize {
+        self.entities().len()
+    }
+
+    /// Returns `true` if no entity is registered.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 /// VecEntityStore is the default vector based implementation of an entity store.
-#[derive(Default)]
-pub struct VecEntityStore {
-    pub inner: Vec<Entity>,
+#[derive(Clone)]
+pub struct VecEntityStore<I = Entity> {
+    pub inner: Vec<I>,
+}
+
+impl<I> Default for VecEntityStore<I> {
+    fn default() -> Self {
+        VecEntityStore { inner: vec![] }
+    }
+}
+
+impl<'a, I: Copy> IntoIterator for &'a VecEntityStore<I> {
+    type Item = I;
+    type IntoIter = Copied<Iter<'a, I>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.iter().copied()
+    }
 }
 
-impl EntityStore for VecEntityStore {
-    fn register_entity(&mut self, entity: impl Into<Entity>) {
+impl<I: PartialEq + Clone> EntityStore<I> for VecEntityStore<I> {
+    fn register_entity(&mut self, entity: impl Into<I>) {
         self.inner.push(entity.into());
     }
 
-    fn remove_entity(&mut self, entity: impl Into<Entity>) {
+    fn remove_entity(&mut self, entity: impl Into<I>) {
         let entity = entity.into();
         self.inner
             .iter()
-            .position(|&n| n == entity)
+            .position(|n| *n == entity)
             .map(|e| self.inner.remove(e));
     }
+
+    fn entities(&self) -> Vec<I> {
+        self.inner.clone()
+    }
+
+    fn contains(&self, entity: I) -> bool {
+        self.inner.contains(&entity)
+    }
+
+    fn retain(&mut self, f: impl FnMut(&I) -> bool) {
+        self.inner.retain(f);
+    }
+
+    fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
 }
 
 #[cfg(test)]
@@ -52,6 +154,18 @@ mod tests {
         assert_eq!(entity.0, 5);
     }
 
+    #[test]
+    fn test_entity_index() {
+        let entity = Entity::from_index(7);
+        assert_eq!(entity.index(), 7);
+        assert_eq!(Entity::from_index(entity.index()), entity);
+        assert_eq!(entity, Entity::from(7));
+
+        assert_eq!(Entity::INVALID.index(), u32::MAX);
+        assert_ne!(Entity::INVALID, Entity(0));
+        assert_ne!(Entity::INVALID, Entity::default());
+    }
+
     #[test]
     fn test_register_entity() {
         let mut store = VecEntityStore::default();
@@ -79,4 +193,62 @@ mod tests {
         assert!(!store.inner.contains(&entity_one));
         assert!(store.inner.contains(&entity_two));
     }
+
+    #[test]
+    fn test_into_iter() {
+        let mut store = VecEntityStore::<Entity>::default();
+        store.register_entity(Entity(2));
+        store.register_entity(Entity(5));
+
+        let mut entities = vec![];
+        for entity in &store {
+            entities.push(entity);
+        }
+        assert_eq!(entities, vec![Entity(2), Entity(5)]);
+    }
+
+    #[test]
+    fn test_len() {
+        let mut store = VecEntityStore::<Entity>::default();
+        assert_eq!((store.len(), store.is_empty()), (0, true));
+
+        store.register_entity(Entity(1));
+        store.register_entity(Entity(2));
+        assert_eq!((store.len(), store.is_empty()), (2, false));
+
+        store.remove_entity(Entity(1));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut store = VecEntityStore::<Entity>::default();
+        store.register_entity(Entity(1));
+        store.register_entity(Entity(2));
+        store.remove_entity(Entity(1));
+
+        assert!(store.contains(Entity(2)));
+        assert!(!store.contains(Entity(1)));
+        assert!(!store.contains(Entity(3)));
+    }
+
+    #[derive(Clone, PartialEq, Debug)]
+    struct WideEntity(u64);
+
+    impl From<u64> for WideEntity {
+        fn from(u: u64) -> Self {
+            WideEntity(u)
+        }
+    }
+
+    #[test]
+    fn test_custom_id() {
+        let mut store = VecEntityStore::<WideEntity>::default();
+        store.register_entity(u64::MAX);
+        store.register_entity(1_u64 << 40);
+
+        store.remove_entity(u64::MAX);
+
+        assert_eq!(store.inner, vec![WideEntity(1 << 40)]);
+    }
 }
@@ -1,4 +1,7 @@
-use core::any::TypeId;
+use core::{any::TypeId, fmt};
+
+#[cfg(feature = "no_std")]
+use alloc::string::String;
 
 use crate::entity::Entity;
 
@@ -16,7 +19,23 @@ pub enum NotFound {
     /// Unknown error
     Unknown(String),
     /// Key could not be found
-    Key((Entity, String))
+    Key((Entity, String)),
+    /// Dependencies of the entity system form a cycle
+    DependencyCycle(u32),
+    /// Stored component is not of the requested type
+    TypeMismatch(TypeId),
+    /// Shared component links of the key form a cycle
+    CyclicShare((Entity, String)),
+    /// Entity owns the component that should be shared
+    OwnedComponent(Entity),
+    /// Component of the type would be borrowed more than once
+    Aliased(TypeId),
+    /// Key of the entity already exists
+    KeyExists((Entity, String)),
+    /// Init system of the world already ran
+    AlreadyInitialized,
+    /// Cleanup system of the world already ran
+    AlreadyCleanedUp,
 }
 
 impl Default for NotFound {
@@ -24,3 +43,97 @@ impl Default for NotFound {
         NotFound::Unknown(String::default())
     }
 }
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotFound::Entity(entity) => write!(f, "entity {} not found", entity.0),
+            NotFound::Component(type_id) => write!(f, "component {:?} not found", type_id),
+            NotFound::EntitySystem(id) => write!(f, "entity system {} not found", id),
+            NotFound::ComponentKey(key) => write!(f, "component key '{}' not found", key),
+            NotFound::Unknown(message) if message.is_empty() => write!(f, "unknown error"),
+            NotFound::Unknown(message) => write!(f, "unknown error: {}", message),
+            NotFound::Key((entity, key)) => {
+                write!(f, "key '{}' of entity {} not found", key, entity.0)
+            }
+            NotFound::DependencyCycle(id) => {
+                write!(f, "dependencies of entity system {} form a cycle", id)
+            }
+            NotFound::TypeMismatch(type_id) => {
+                write!(f, "component is not of the requested type {:?}", type_id)
+            }
+            NotFound::CyclicShare((entity, key)) => {
+                write!(
+                    f,
+                    "shared key '{}' of entity {} forms a cycle",
+                    key, entity.0
+                )
+            }
+            NotFound::OwnedComponent(entity) => {
+                write!(f, "entity {} owns the component", entity.0)
+            }
+            NotFound::Aliased(type_id) => {
+                write!(f, "component {:?} is borrowed more than once", type_id)
+            }
+            NotFound::KeyExists((entity, key)) => {
+                write!(f, "key '{}' of entity {} already exists", key, entity.0)
+            }
+            NotFound::AlreadyInitialized => write!(f, "init system already ran"),
+            NotFound::AlreadyCleanedUp => write!(f, "cleanup system already ran"),
+        }
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl std::error::Error for NotFound {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            NotFound::Entity(Entity(5)).to_string(),
+            "entity 5 not found"
+        );
+        assert_eq!(
+            NotFound::ComponentKey(String::from("size")).to_string(),
+            "component key 'size' not found"
+        );
+        assert_eq!(
+            NotFound::Key((Entity(2), String::from("size"))).to_string(),
+            "key 'size' of entity 2 not found"
+        );
+        assert_eq!(
+            NotFound::CyclicShare((Entity(2), String::from("size"))).to_string(),
+            "shared key 'size' of entity 2 forms a cycle"
+        );
+        assert_eq!(
+            NotFound::KeyExists((Entity(2), String::from("size"))).to_string(),
+            "key 'size' of entity 2 already exists"
+        );
+        assert_eq!(
+            NotFound::OwnedComponent(Entity(3)).to_string(),
+            "entity 3 owns the component"
+        );
+        assert_eq!(
+            NotFound::AlreadyInitialized.to_string(),
+            "init system already ran"
+        );
+        assert_eq!(NotFound::default().to_string(), "unknown error");
+        assert!(NotFound::TypeMismatch(TypeId::of::<u32>())
+            .to_string()
+            .starts_with("component is not of the requested type"));
+        assert!(NotFound::Aliased(TypeId::of::<u32>())
+            .to_string()
+            .ends_with("is borrowed more than once"));
+    }
+
+    #[cfg(not(feature = "no_std"))]
+    #[test]
+    fn error() {
+        let error: Box<dyn std::error::Error> = Box::new(NotFound::EntitySystem(3));
+        assert_eq!(error.to_string(), "entity system 3 not found");
+    }
+}
@@ -1,6 +1,7 @@
 #![crate_name = "dces"]
 #![crate_type = "lib"]
 #![deny(warnings)]
+#![cfg_attr(all(feature = "no_std", not(test)), no_std)]
 
 //! # DCES
 //!
@@ -58,6 +59,12 @@
 //!
 //!
 //! ```
+
+#[cfg(feature = "no_std")]
+#[macro_use]
+extern crate alloc;
+
+pub mod commands;
 pub mod component;
 pub mod entity;
 pub mod error;
@@ -1,11 +1,12 @@
 pub use crate::{
+    commands::Commands,
     component::{
-        Component, ComponentBox, EntityBuilder, EntityComponentManager, SharedComponentBox,
-        StringComponentBuilder, StringComponentStore, TypeComponentBuilder as ComponentBuilder,
-        TypeComponentStore as ComponentStore,
+        CloneRegistry, Component, ComponentBox, DefaultRegistry, EntityBuilder,
+        EntityComponentManager, SharedComponentBox, StringComponentBuilder, StringComponentStore,
+        TypeComponentBuilder as ComponentBuilder, TypeComponentStore as ComponentStore,
     },
     entity::{Entity, VecEntityStore as EntityStore},
     error::NotFound,
     system::{Priority, System},
-    world::World,
+    world::{World, WorldSnapshot},
 };
@@ -1,10 +1,22 @@
-use core::{any::Any, cell::Cell};
+use core::{
+    any::Any,
+    cell::{Cell, Ref, RefCell},
+    cmp::Ordering,
+};
 
 #[cfg(not(feature = "no_std"))]
 use std::collections::{BTreeMap, HashMap};
 
 #[cfg(feature = "no_std")]
-use alloc::collections::{BTreeMap, HashMap};
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+#[cfg(feature = "no_std")]
+use hashbrown::HashMap;
 
 use crate::{component::*, entity::*, error::NotFound};
 
@@ -19,14 +31,44 @@ where
     C: ComponentStore,
 {
     fn run(&self, ecm: &mut EntityComponentManager<E, C>);
+
+    /// Returns the keys of the components the system reads. Default is empty. The keys are
+    /// metadata for `SystemStore::access_matrix`, systems never run in parallel.
+    fn reads(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Returns the keys of the components the system writes. Default is empty.
+    fn writes(&self) -> &[&str] {
+        &[]
+    }
 }
 
+/// Selects the entities a system is interested in.
+pub type EntityFilter<C> = Box<dyn Fn(Entity, &C) -> bool>;
+
+/// Compares two entities to define the order a system gets its entities in.
+pub type EntitySort<C> = Box<dyn Fn(Entity, Entity, &C) -> Ordering>;
+
 /// Internal wrapper for a system. Contains also filter, priority, sort and entities.
 pub struct EntitySystem<E, C> {
     /// The wrapped system.
     pub system: Box<dyn System<E, C>>,
 
     priority: Priority,
+
+    enabled: bool,
+
+    stage: Option<String>,
+
+    sub_steps: usize,
+
+    filter: Option<EntityFilter<C>>,
+
+    sort: Option<EntitySort<C>>,
+
+    // The last unsorted entities and their sorted order.
+    sorted: RefCell<(Vec<Entity>, Vec<Entity>)>,
 }
 
 impl<E, C> EntitySystem<E, C> {
@@ -35,7 +77,60 @@ impl<E, C> EntitySystem<E, C> {
         EntitySystem {
             system,
             priority: 0,
+            enabled: true,
+            stage: None,
+            sub_steps: 1,
+            filter: None,
+            sort: None,
+            sorted: RefCell::new((vec![], vec![])),
+        }
+    }
+
+    /// Returns the priority of the system.
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Returns `true` if the system is executed on run.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns how many times the system is executed on each run.
+    pub fn sub_steps(&self) -> usize {
+        self.sub_steps
+    }
+
+    /// Returns `true` if the system has a filter.
+    pub fn has_filter(&self) -> bool {
+        self.filter.is_some()
+    }
+
+    /// Returns `true` if the system has a sort.
+    pub fn has_sort(&self) -> bool {
+        self.sort.is_some()
+    }
+
+    /// Returns the entities of the `entity_store` that pass the filter of the system in the
+    /// order of its sort. Without a filter all entities are returned. The sorted order is cached
+    /// and only sorted again if the entities change.
+    pub fn entities(&self, entity_store: &impl EntityStore, component_store: &C) -> Vec<Entity> {
+        let mut entities = entity_store.entities();
+        if let Some(filter) = &self.filter {
+            entities.retain(|entity| filter(*entity, component_store));
+        }
+
+        if let Some(sort) = &self.sort {
+            let mut sorted = self.sorted.borrow_mut();
+            if sorted.0 != entities {
+                let mut order = entities.clone();
+                order.sort_by(|a, b| sort(*a, *b, component_store));
+                *sorted = (entities, order);
+            }
+            return sorted.1.clone();
         }
+
+        entities
     }
 }
 
@@ -54,6 +149,9 @@ where
 
     // Priority of the entity system.
     pub priority: Cell<i32>,
+
+    // Dependencies of the entity system as pairs of (before, after) system ids.
+    pub dependencies: RefCell<Vec<(u32, u32)>>,
 }
 
 impl<'a, E, C> SystemStoreBuilder<'a, E, C>
@@ -67,11 +165,76 @@ where
         self
     }
 
-    /// Finishing the creation of the system.
-    pub fn build(self) -> u32 {
+    /// Moves the system into the stage with the given `name`. An unknown stage is added after
+    /// all existing stages.
+    pub fn in_stage(self, name: &str) -> Self {
+        self.system_store.set_stage(self.entity_system_id, name);
+        self
+    }
+
+    /// Only passes the entities accepted by `filter` to the system. The system reads them with
+    /// `EntityComponentManager::system_entities`.
+    pub fn with_filter(self, filter: impl Fn(Entity, &C) -> bool + 'static) -> Self {
+        self.system_store
+            .set_filter(self.entity_system_id, Box::new(filter));
+        self
+    }
+
+    /// Passes the entities to the system in the order defined by `sort`. The order is only
+    /// sorted again if the entities change.
+    pub fn with_sort(self, sort: impl Fn(Entity, Entity, &C) -> Ordering + 'static) -> Self {
+        self.system_store
+            .set_sort(self.entity_system_id, Box::new(sort));
+        self
+    }
+
+    /// Executes the system `n` times on each run, e.g. for fixed time step integration. Default
+    /// is 1.
+    pub fn sub_steps(self, n: usize) -> Self {
+        self.system_store.set_sub_steps(self.entity_system_id, n);
+        self
+    }
+
+    /// Runs the system after the system with the given `system_id`.
+    pub fn after(self, system_id: u32) -> Self {
+        self.dependencies
+            .borrow_mut()
+            .push((system_id, self.entity_system_id));
+        self
+    }
+
+    /// Runs the system before the system with the given `system_id`.
+    pub fn before(self, system_id: u32) -> Self {
+        self.dependencies
+            .borrow_mut()
+            .push((self.entity_system_id, system_id));
+        self
+    }
+
+    /// Finishing the creation of the system. If the dependencies of the system form a cycle the
+    /// system is removed from the store and `NotFound::DependencyCycle` will be returned.
+    pub fn try_build(self) -> Result<u32, NotFound> {
         self.system_store
             .register_priority(self.priority.get(), self.entity_system_id);
-        self.entity_system_id
+
+        for (before, after) in self.dependencies.borrow().iter() {
+            if let Err(error) = self.system_store.register_dependency(*before, *after) {
+                self.system_store.remove_system(self.entity_system_id);
+                return Err(error);
+            }
+        }
+
+        Ok(self.entity_system_id)
+    }
+
+    /// Finishing the creation of the system. **Panics on a dependency cycle.**
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dependencies of the system form a cycle. Use `try_build` to handle this case.
+    pub fn build(self) -> u32 {
+        self.try_build()
+            .expect("SystemStoreBuilder.build: dependency cycle")
     }
 }
 
@@ -91,8 +254,17 @@ where
     // The cleanup system.
     cleanup_system: Option<EntitySystem<E, C>>,
 
-    /// Priorities of the systems.
-    pub priorities: BTreeMap<i32, Vec<u32>>,
+    // Priorities of the systems.
+    priorities: BTreeMap<i32, Vec<u32>>,
+
+    // Dependencies between systems as pairs of (before, after) system ids.
+    dependencies: Vec<(u32, u32)>,
+
+    // Names of the stages in execution order.
+    stages: Vec<String>,
+
+    // Cached execution order. It is reset when systems, priorities, dependencies or stages change.
+    order: RefCell<Option<Vec<u32>>>,
 }
 
 impl<E, C> SystemStore<E, C>
@@ -107,9 +279,17 @@ where
             init_system: None,
             cleanup_system: None,
             priorities: BTreeMap::new(),
+            dependencies: vec![],
+            stages: vec![],
+            order: RefCell::new(None),
         }
     }
 
+    // Resets the cached execution order.
+    fn reset_order(&mut self) {
+        *self.order.get_mut() = None;
+    }
+
     /// Registers the init system.
     pub fn register_init_system(&mut self, init_system: impl System<E, C>) {
         self.init_system = Some(EntitySystem::new(Box::new(init_system)));
@@ -122,22 +302,292 @@ where
 
     /// Registers a new `system`.
     pub fn register_system(&mut self, system: impl System<E, C>, system_id: u32) {
+        self.register_boxed_system(Box::new(system), system_id);
+    }
+
+    /// Registers a new already boxed `system`.
+    pub fn register_boxed_system(&mut self, system: Box<dyn System<E, C>>, system_id: u32) {
+        self.reset_order();
+        self.entity_systems
+            .insert(system_id, EntitySystem::new(system));
+    }
+
+    /// Registers a new `system` in the stage with the given `name`. An unknown stage is added
+    /// after all existing stages.
+    pub fn register_system_in_stage(
+        &mut self,
+        system: impl System<E, C>,
+        system_id: u32,
+        name: &str,
+    ) {
+        self.register_system(system, system_id);
+        self.set_stage(system_id, name);
+    }
+
+    /// Adds a stage with the given `name` after all existing stages. Does nothing if the stage
+    /// already exists.
+    pub fn add_stage(&mut self, name: &str) {
+        if !self.stages.iter().any(|s| s == name) {
+            self.reset_order();
+            self.stages.push(name.to_string());
+        }
+    }
+
+    /// Inserts a stage with the given `name` at position `index` of the stage order. An existing
+    /// stage with the same name is moved.
+    pub fn insert_stage(&mut self, index: usize, name: &str) {
+        self.reset_order();
+        self.stages.retain(|s| s != name);
+        self.stages
+            .insert(index.min(self.stages.len()), name.to_string());
+    }
+
+    /// Returns the names of all stages in execution order.
+    pub fn stages(&self) -> &[String] {
+        &self.stages
+    }
+
+    /// Moves the system with the given `system_id` into the stage with the given `name`. An
+    /// unknown stage is added after all existing stages.
+    pub fn set_stage(&mut self, system_id: u32, name: &str) {
+        self.add_stage(name);
+        self.reset_order();
+
+        if let Some(entity_system) = self.entity_systems.get_mut(&system_id) {
+            entity_system.stage = Some(name.to_string());
+        }
+    }
+
+    /// Returns the ids of the systems in the stage with the given `name` in execution order.
+    pub fn stage_systems(&self, name: &str) -> Vec<u32> {
+        self.cached_execution_order()
+            .iter()
+            .copied()
+            .filter(|id| {
+                self.entity_systems
+                    .get(id)
+                    .is_some_and(|s| s.stage.as_deref() == Some(name))
+            })
+            .collect()
+    }
+
+    // Returns the position of the system's stage in the execution order. Systems without a stage
+    // run before all stages.
+    fn stage_index(&self, system_id: u32) -> usize {
         self.entity_systems
-            .insert(system_id, EntitySystem::new(Box::new(system)));
+            .get(&system_id)
+            .and_then(|s| s.stage.as_ref())
+            .and_then(|stage| self.stages.iter().position(|s| s == stage))
+            .map_or(0, |index| index + 1)
+    }
+
+    /// Removes all entity systems with their priorities, dependencies and stages but keeps the
+    /// allocated capacity. The init and cleanup systems are kept.
+    pub fn clear(&mut self) {
+        self.reset_order();
+        self.entity_systems.clear();
+        self.priorities.clear();
+        self.dependencies.clear();
+        self.stages.clear();
     }
 
-    /// Removes a system from the storage.
+    /// Removes a system from the storage. Other systems with the same priority are not affected.
     pub fn remove_system(&mut self, system_id: u32) {
-        self.entity_systems.remove(&system_id);
+        self.reset_order();
+        if let Some(entity_system) = self.entity_systems.remove(&system_id) {
+            if let Some(systems) = self.priorities.get_mut(&entity_system.priority) {
+                systems.retain(|id| *id != system_id);
+
+                if systems.is_empty() {
+                    self.priorities.remove(&entity_system.priority);
+                }
+            }
+        }
+
+        self.dependencies
+            .retain(|(before, after)| *before != system_id && *after != system_id);
+    }
+
+    /// Registers that the system `before` has to run before the system `after`. If this results
+    /// in a cycle the dependency is not registered and `NotFound::DependencyCycle` will be
+    /// returned.
+    pub fn register_dependency(&mut self, before: u32, after: u32) -> Result<(), NotFound> {
+        self.reset_order();
+        self.dependencies.push((before, after));
+
+        if let Err(error) = self.sort() {
+            self.dependencies.pop();
+            return Err(error);
+        }
+
+        Ok(())
     }
 
-    /// Register a `priority` for the system with the given `system_id`.
+    /// Enables or disables the system with the given `system_id`. A disabled system keeps its
+    /// id and priority but is skipped on run.
+    pub fn set_enabled(&mut self, system_id: u32, enabled: bool) {
+        if let Some(entity_system) = self.entity_systems.get_mut(&system_id) {
+            entity_system.enabled = enabled;
+        }
+    }
+
+    /// Sets how many times the system with the given `system_id` is executed on each run.
+    pub fn set_sub_steps(&mut self, system_id: u32, n: usize) {
+        if let Some(entity_system) = self.entity_systems.get_mut(&system_id) {
+            entity_system.sub_steps = n;
+        }
+    }
+
+    /// Sets the entity `filter` of the system with the given `system_id`.
+    pub fn set_filter(&mut self, system_id: u32, filter: EntityFilter<C>) {
+        if let Some(entity_system) = self.entity_systems.get_mut(&system_id) {
+            entity_system.filter = Some(filter);
+        }
+    }
+
+    /// Sets the entity `sort` of the system with the given `system_id`.
+    pub fn set_sort(&mut self, system_id: u32, sort: EntitySort<C>) {
+        if let Some(entity_system) = self.entity_systems.get_mut(&system_id) {
+            entity_system.sort = Some(sort);
+            entity_system.sorted = RefCell::new((vec![], vec![]));
+        }
+    }
+
+    /// Register a `priority` for the system with the given `system_id`. Systems with the same
+    /// priority run in the order they are registered.
     pub fn register_priority(&mut self, priority: Priority, system_id: u32) {
+        self.reset_order();
         self.entity_systems.get_mut(&system_id).unwrap().priority = priority;
-        self.priorities
-            .entry(priority)
-            .or_insert_with(|| vec![])
-            .push(system_id);
+        self.priorities.entry(priority).or_default().push(system_id);
+    }
+
+    /// Moves the system with the given `system_id` to the new `priority`. The system runs after
+    /// the systems that already have this priority.
+    pub fn set_priority(&mut self, system_id: u32, priority: Priority) {
+        let old = match self.entity_systems.get(&system_id) {
+            Some(entity_system) => entity_system.priority,
+            None => return,
+        };
+
+        if let Some(systems) = self.priorities.get_mut(&old) {
+            systems.retain(|id| *id != system_id);
+
+            if systems.is_empty() {
+                self.priorities.remove(&old);
+            }
+        }
+
+        self.register_priority(priority, system_id);
+    }
+
+    /// Returns the ids of all systems in execution order. Systems without a stage run first,
+    /// followed by the stages in their order. Inside of a stage the systems are ordered by
+    /// priority and systems with the same priority by registration. Dependencies registered with
+    /// `register_dependency` take precedence over stages and priority. The order is cached until
+    /// the systems change.
+    pub fn execution_order(&self) -> Vec<u32> {
+        self.cached_execution_order().to_vec()
+    }
+
+    // Returns the cached execution order without copying it. It is sorted on first use.
+    pub(crate) fn cached_execution_order(&self) -> Ref<'_, [u32]> {
+        if self.order.borrow().is_none() {
+            let order = self.sort();
+
+            // register_dependency rejects cycles, so the sort could not fail
+            debug_assert!(order.is_ok(), "SystemStore.execution_order: {:?}", order);
+            *self.order.borrow_mut() = Some(
+                order.unwrap_or_else(|_| self.priorities.values().flatten().copied().collect()),
+            );
+        }
+
+        Ref::map(self.order.borrow(), |order| order.as_deref().unwrap_or(&[]))
+    }
+
+    /// Returns the ids of the systems grouped by their priority.
+    pub fn priorities(&self) -> &BTreeMap<i32, Vec<u32>> {
+        &self.priorities
+    }
+
+    /// Returns the ids of all systems in execution order without running them. If a priority
+    /// references a system that is not registered `NotFound::EntitySystem` will be returned and
+    /// on a dependency cycle `NotFound::DependencyCycle`.
+    pub fn dry_run(&self) -> Result<Vec<u32>, NotFound> {
+        if let Some(id) = self
+            .priorities
+            .values()
+            .flatten()
+            .find(|id| !self.entity_systems.contains_key(*id))
+        {
+            return Err(NotFound::EntitySystem(*id));
+        }
+
+        self.sort()
+    }
+
+    // Sorts the systems topologically by their dependencies. Ties are broken by priority.
+    fn sort(&self) -> Result<Vec<u32>, NotFound> {
+        let mut pending: Vec<u32> = self.priorities.values().flatten().copied().collect();
+        pending.sort_by_key(|id| self.stage_index(*id));
+        let mut order = Vec::with_capacity(pending.len());
+
+        while !pending.is_empty() {
+            let index = pending
+                .iter()
+                .position(|id| {
+                    !self
+                        .dependencies
+                        .iter()
+                        .any(|(before, after)| after == id && pending.contains(before))
+                })
+                .ok_or(NotFound::DependencyCycle(pending[0]))?;
+
+            order.push(pending.remove(index));
+        }
+
+        Ok(order)
+    }
+
+    /// Returns the ids of all registered systems sorted by id. Use `execution_order` for the
+    /// order the systems run in.
+    pub fn system_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.entity_systems.keys().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Returns the id, priority and enabled state of all registered systems sorted by id.
+    pub fn systems(&self) -> Vec<(u32, Priority, bool)> {
+        let mut systems: Vec<(u32, Priority, bool)> = self
+            .entity_systems
+            .iter()
+            .map(|(id, entity_system)| (*id, entity_system.priority, entity_system.enabled))
+            .collect();
+        systems.sort_by_key(|s| s.0);
+        systems
+    }
+
+    /// Returns the id with the read and written component keys of all registered systems sorted
+    /// by id, e.g. to render a diagram of the data flow between systems.
+    pub fn access_matrix(&self) -> Vec<(u32, Vec<String>, Vec<String>)>
+    where
+        E: 'static,
+        C: 'static,
+    {
+        let to_strings = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect();
+        let mut matrix: Vec<(u32, Vec<String>, Vec<String>)> = self
+            .entity_systems
+            .iter()
+            .map(|(id, entity_system)| {
+                (
+                    *id,
+                    to_strings(entity_system.system.reads()),
+                    to_strings(entity_system.system.writes()),
+                )
+            })
+            .collect();
+        matrix.sort_by_key(|m| m.0);
+        matrix
     }
 
     /// Returns a reference of a entity system. If the entity system does not exists `NotFound` will be returned.
@@ -173,6 +623,54 @@ mod tests {
         fn run(&self, _ecm: &mut EntityComponentManager<VecEntityStore, TypeComponentStore>) {}
     }
 
+    struct AccessSystem;
+
+    impl System<VecEntityStore, TypeComponentStore> for AccessSystem {
+        fn run(&self, _ecm: &mut EntityComponentManager<VecEntityStore, TypeComponentStore>) {}
+
+        fn reads(&self) -> &[&str] {
+            &["size", "depth"]
+        }
+
+        fn writes(&self) -> &[&str] {
+            &["position"]
+        }
+    }
+
+    #[test]
+    fn test_system_access() {
+        let mut esm = SystemStore::new();
+        esm.register_system(TestSystem, 0);
+        esm.register_system(AccessSystem, 1);
+
+        let system = &esm.borrow_entity_system(0).unwrap().system;
+        assert!(system.reads().is_empty());
+        assert!(system.writes().is_empty());
+
+        let system = &esm.borrow_entity_system(1).unwrap().system;
+        assert_eq!(system.reads(), &["size", "depth"]);
+        assert_eq!(system.writes(), &["position"]);
+    }
+
+    #[test]
+    fn access_matrix() {
+        let mut esm = SystemStore::new();
+        esm.register_system(AccessSystem, 1);
+        esm.register_system(TestSystem, 0);
+
+        assert_eq!(
+            esm.access_matrix(),
+            vec![
+                (0, vec![], vec![]),
+                (
+                    1,
+                    vec![String::from("size"), String::from("depth")],
+                    vec![String::from("position")]
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn test_register_system() {
         let mut esm = SystemStore::new();
@@ -181,6 +679,19 @@ mod tests {
         assert!(esm.entity_systems.contains_key(&0));
     }
 
+    #[test]
+    fn test_register_boxed_system() {
+        let mut esm = SystemStore::new();
+        let systems: Vec<Box<dyn System<VecEntityStore, TypeComponentStore>>> =
+            vec![Box::new(TestSystem), Box::new(TestSystem)];
+        for (id, system) in systems.into_iter().enumerate() {
+            esm.register_boxed_system(system, id as u32);
+        }
+
+        assert!(esm.entity_systems.contains_key(&0));
+        assert!(esm.entity_systems.contains_key(&1));
+    }
+
     #[test]
     fn test_register_init_system() {
         let mut esm = SystemStore::new();
@@ -210,6 +721,39 @@ mod tests {
         assert!(!esm.entity_systems.contains_key(&0));
     }
 
+    #[test]
+    fn test_remove_system_keeps_priority() {
+        let mut esm = SystemStore::new();
+        for id in 0..3 {
+            esm.register_system(TestSystem, id);
+            esm.register_priority(0, id);
+        }
+        esm.register_system(TestSystem, 3);
+        esm.register_priority(1, 3);
+
+        esm.remove_system(1);
+        assert_eq!(esm.priorities.get(&0), Some(&vec![0, 2]));
+
+        esm.remove_system(3);
+        assert!(!esm.priorities.contains_key(&1));
+    }
+
+    #[test]
+    fn test_dry_run_stale_priority() {
+        let mut esm = SystemStore::new();
+        esm.register_system(TestSystem, 0);
+        esm.register_priority(0, 0);
+        esm.register_system(TestSystem, 1);
+        esm.register_priority(0, 1);
+        assert_eq!(esm.dry_run(), Ok(vec![0, 1]));
+
+        // a removal that leaves its id behind in the priority bucket
+        esm.remove_system(1);
+        esm.priorities.entry(0).or_default().push(1);
+
+        assert_eq!(esm.dry_run(), Err(NotFound::EntitySystem(1)));
+    }
+
     #[test]
     fn test_register_priority() {
         let mut esm = SystemStore::new();
@@ -220,6 +764,165 @@ mod tests {
         assert!(esm.priorities.contains_key(&5));
     }
 
+    #[test]
+    fn test_set_priority() {
+        let mut esm = SystemStore::new();
+        for id in 0..3 {
+            esm.register_system(TestSystem, id);
+            esm.register_priority(id as i32, id);
+        }
+        assert_eq!(esm.execution_order(), vec![0, 1, 2]);
+
+        esm.set_priority(0, 3);
+        esm.set_priority(2, 1);
+
+        assert_eq!(esm.execution_order(), vec![1, 2, 0]);
+        assert_eq!(esm.priorities.get(&1), Some(&vec![1, 2]));
+        assert!(!esm.priorities.contains_key(&0));
+        assert!(!esm.priorities.contains_key(&2));
+        assert_eq!(esm.borrow_entity_system(0).unwrap().priority(), 3);
+    }
+
+    #[test]
+    fn test_execution_order_cache() {
+        let mut esm = SystemStore::new();
+        for id in 0..3 {
+            esm.register_system(TestSystem, id);
+            esm.register_priority(0, id);
+        }
+        assert_eq!(esm.execution_order(), vec![0, 1, 2]);
+        assert!(esm.order.borrow().is_some());
+
+        esm.set_priority(0, 1);
+        assert!(esm.order.borrow().is_none());
+        assert_eq!(esm.execution_order(), vec![1, 2, 0]);
+
+        esm.register_dependency(0, 1).unwrap();
+        assert_eq!(esm.execution_order(), vec![2, 0, 1]);
+
+        esm.remove_system(2);
+        assert_eq!(esm.execution_order(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_system_ids() {
+        let mut esm = SystemStore::new();
+        for (id, priority) in [(0, 2), (1, -1), (2, 1)] {
+            esm.register_system(TestSystem, id);
+            esm.register_priority(priority, id);
+        }
+
+        assert_eq!(esm.system_ids(), vec![0, 1, 2]);
+        assert_eq!(esm.execution_order(), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn test_execution_order() {
+        let mut esm = SystemStore::new();
+        esm.register_system(TestSystem, 0);
+        esm.register_system(TestSystem, 1);
+        esm.register_system(TestSystem, 2);
+        esm.register_priority(1, 0);
+        esm.register_priority(0, 1);
+        esm.register_priority(1, 2);
+
+        assert_eq!(esm.execution_order(), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_set_enabled() {
+        let mut esm = SystemStore::new();
+        esm.register_system(TestSystem, 0);
+        esm.register_priority(2, 0);
+        assert!(esm.borrow_entity_system(0).unwrap().is_enabled());
+
+        esm.set_enabled(0, false);
+        assert!(!esm.borrow_entity_system(0).unwrap().is_enabled());
+        assert_eq!(esm.priorities.get(&2), Some(&vec![0]));
+
+        esm.set_enabled(0, true);
+        assert!(esm.borrow_entity_system(0).unwrap().is_enabled());
+    }
+
+    #[test]
+    fn test_register_dependency() {
+        let mut esm = SystemStore::new();
+        for id in 0..3 {
+            esm.register_system(TestSystem, id);
+            esm.register_priority(2 - id as i32, id);
+        }
+
+        assert_eq!(esm.execution_order(), vec![2, 1, 0]);
+
+        assert!(esm.register_dependency(0, 1).is_ok());
+        assert!(esm.register_dependency(1, 2).is_ok());
+        assert_eq!(esm.execution_order(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_register_dependency_cycle() {
+        let mut esm = SystemStore::new();
+        for id in 0..3 {
+            esm.register_system(TestSystem, id);
+            esm.register_priority(0, id);
+        }
+
+        assert!(esm.register_dependency(0, 1).is_ok());
+        assert!(esm.register_dependency(1, 2).is_ok());
+        assert!(esm.register_dependency(2, 0).is_err());
+        assert_eq!(esm.execution_order(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_build_cycle() {
+        let mut esm = SystemStore::new();
+        esm.register_system(TestSystem, 0);
+        esm.register_priority(0, 0);
+        esm.register_system(TestSystem, 1);
+
+        let result = SystemStoreBuilder {
+            entity_system_id: 1,
+            system_store: &mut esm,
+            priority: Cell::new(0),
+            dependencies: RefCell::new(vec![]),
+        }
+        .after(0)
+        .before(0)
+        .try_build();
+
+        assert!(result.is_err());
+        assert!(esm.borrow_entity_system(1).is_err());
+        assert_eq!(esm.execution_order(), vec![0]);
+    }
+
+    #[test]
+    fn test_stages() {
+        let mut esm = SystemStore::new();
+        esm.add_stage("update");
+        esm.register_system_in_stage(TestSystem, 0, "render");
+        esm.register_priority(-5, 0);
+        esm.register_system_in_stage(TestSystem, 1, "update");
+        esm.register_priority(10, 1);
+        esm.register_system(TestSystem, 2);
+        esm.register_priority(20, 2);
+        esm.insert_stage(0, "input");
+
+        assert_eq!(esm.stages(), &["input", "update", "render"]);
+        assert_eq!(esm.execution_order(), vec![2, 1, 0]);
+        assert_eq!(esm.stage_systems("update"), vec![1]);
+        assert!(esm.stage_systems("input").is_empty());
+    }
+
+    #[test]
+    fn test_set_sub_steps() {
+        let mut esm = SystemStore::new();
+        esm.register_system(TestSystem, 0);
+        assert_eq!(esm.borrow_entity_system(0).unwrap().sub_steps(), 1);
+
+        esm.set_sub_steps(0, 3);
+        assert_eq!(esm.borrow_entity_system(0).unwrap().sub_steps(), 3);
+    }
+
     #[test]
     fn test_borrow_init_entity_system() {
         let mut esm = SystemStore::new();
@@ -254,9 +957,43 @@ mod tests {
                 entity_system_id: 0,
                 system_store: &mut esm,
                 priority: Cell::new(0),
+                dependencies: RefCell::new(vec![]),
             };
 
             assert_eq!(esb.build(), 0);
         }
     }
+
+    #[test]
+    fn clear() {
+        let mut esm = SystemStore::new();
+        esm.register_init_system(TestSystem);
+        esm.register_system(TestSystem, 0);
+        esm.register_system(TestSystem, 1);
+        esm.register_priority(1, 1);
+        esm.register_dependency(0, 1).unwrap();
+        esm.add_stage("layout");
+
+        esm.clear();
+
+        assert!(esm.systems().is_empty());
+        assert!(esm.priorities.is_empty());
+        assert!(esm.stages().is_empty());
+        assert!(esm.execution_order().is_empty());
+        assert!(esm.borrow_init_system().is_some());
+    }
+
+    #[test]
+    fn dry_run() {
+        let mut esm = SystemStore::new();
+        esm.register_system(TestSystem, 0);
+        esm.register_system(TestSystem, 1);
+        esm.register_priority(0, 0);
+        esm.register_priority(-1, 1);
+        assert_eq!(esm.dry_run(), Ok(vec![1, 0]));
+
+        esm.register_dependency(1, 0).unwrap();
+        esm.dependencies.push((0, 1));
+        assert_eq!(esm.dry_run(), Err(NotFound::DependencyCycle(1)));
+    }
 }
@@ -1,50 +1,66 @@
-use core::cell::Cell;
+use core::any::Any;
+use core::cell::{Cell, RefCell};
 use core::ops::Drop;
 
+#[cfg(not(feature = "no_std"))]
+use std::collections::BTreeSet;
+
+#[cfg(feature = "no_std")]
+use alloc::{boxed::Box, collections::BTreeSet, string::String, vec::Vec};
+
 use crate::{
     component::*,
     entity::*,
-    system::{System, SystemStore, SystemStoreBuilder},
+    error::NotFound,
+    system::{Priority, System, SystemStore, SystemStoreBuilder},
 };
 
+/// The components of an entity exported by `World::export_entities` as pairs of key and
+/// component.
+pub type ExportedComponents<'a, 'b> = Vec<(&'a str, &'b dyn Any)>;
+
+/// A deep copy of the entity store, the component store and the entity counter of a world.
+/// It is created by `World::snapshot` and applied with `World::restore`.
+pub struct WorldSnapshot<E, C> {
+    entity_store: E,
+    component_store: C,
+    entity_counter: u32,
+}
+
 /// The `World` struct represents the main interface of the library. It used
 /// as storage of entities, components and systems.
 pub struct World<E, C>
 where
-    E: EntityStore,
-    C: ComponentStore,
+    E: EntityStore + 'static,
+    C: ComponentStore + 'static,
 {
     entity_component_manager: EntityComponentManager<E, C>,
     system_store: SystemStore<E, C>,
     system_counter: u32,
     first_run: bool,
+    cleaned_up: bool,
+    paused: bool,
+    tick: Tick,
+    clone_registry: CloneRegistry,
+    type_names: TypeNameRegistry,
 }
 
 impl<E, C> Drop for World<E, C>
 where
-    E: EntityStore,
-    C: ComponentStore,
+    E: EntityStore + 'static,
+    C: ComponentStore + 'static,
 {
     fn drop(&mut self) {
-        if let Some(cleanup_system) = self.system_store.borrow_cleanup_system() {
-            cleanup_system
-                .system
-                .run(&mut self.entity_component_manager);
+        if !self.cleaned_up {
+            self.run_cleanup_system();
         }
     }
 }
 
-unsafe impl<E, C> Send for World<E, C>
-where
-    E: EntityStore,
-    C: ComponentStore,
-{
-}
-
 impl<E, C> World<E, C>
 where
-    E: EntityStore,
-    C: ComponentStore,
+    E: EntityStore + 'static,
+    C: ComponentStore + 'static,
 {
     /// Creates a new world from the given container.
     pub fn from_stores(entity_store: E, component_store: C) -> Self {
@@ -53,6 +69,11 @@ where
             system_store: SystemStore::new(),
             system_counter: 0,
             first_run: true,
+            cleaned_up: false,
+            paused: false,
+            tick: 0,
+            clone_registry: CloneRegistry::default(),
+            type_names: TypeNameRegistry::default(),
         }
     }
 
@@ -61,11 +82,47 @@ where
         self.entity_component_manager.create_entity()
     }
 
+    /// Creates `n` new entities without components and returns them.
+    pub fn create_entities(&mut self, n: usize) -> Vec<Entity> {
+        (0..n).map(|_| self.create_entity().build()).collect()
+    }
+
     /// Deletes the given `entity`.
     pub fn remove_entity(&mut self, entity: impl Into<Entity>) {
         self.entity_component_manager.remove_entity(entity);
     }
 
+    /// Removes all entities and returns them in the order of the entity store. Removal hooks
+    /// run for each entity.
+    pub fn drain_entities(&mut self) -> Vec<Entity> {
+        let entities = self.entity_component_manager.stores().0.entities();
+        for entity in &entities {
+            self.entity_component_manager.remove_entity(*entity);
+        }
+        entities
+    }
+
+    /// Removes all entities for which `f` returns `false` with their components in one pass.
+    /// Shared links that point to a removed entity are removed too.
+    pub fn retain_entities(&mut self, f: impl Fn(Entity, &C) -> bool) {
+        self.entity_component_manager.retain_entities(f);
+    }
+
+    /// Returns the number of entities in the world.
+    pub fn entity_count(&self) -> usize {
+        self.entity_component_manager.stores().0.len()
+    }
+
+    /// Returns `true` if the world has no entities.
+    pub fn is_empty(&self) -> bool {
+        self.entity_component_manager.stores().0.is_empty()
+    }
+
+    /// Returns `true` if the given `entity` exists.
+    pub fn contains_entity(&self, entity: Entity) -> bool {
+        self.entity_component_manager.contains_entity(entity)
+    }
+
     /// Registers the init system.
     pub fn register_init_system(&mut self, init_system: impl System<E, C>) {
         self.system_store.register_init_system(init_system);
@@ -78,63 +135,405 @@ where
 
     /// Creates a new entity system and returns a returns an `SystemStoreBuilder`.
     pub fn create_system(&mut self, system: impl System<E, C>) -> SystemStoreBuilder<'_, E, C> {
+        self.create_boxed_system(Box::new(system))
+    }
+
+    /// Creates a new entity system from an already boxed `system` and returns a
+    /// `SystemStoreBuilder`.
+    pub fn create_boxed_system(
+        &mut self,
+        system: Box<dyn System<E, C>>,
+    ) -> SystemStoreBuilder<'_, E, C> {
         let entity_system_id = self.system_counter;
-        self.system_store.register_system(system, entity_system_id);
+        self.system_store
+            .register_boxed_system(system, entity_system_id);
         self.system_counter += 1;
 
         SystemStoreBuilder {
             system_store: &mut self.system_store,
             entity_system_id,
             priority: Cell::new(0),
+            dependencies: RefCell::new(vec![]),
         }
     }
 
+    /// Adds a stage with the given `name` after all existing stages. Stages run in the order they
+    /// are added and systems are moved into a stage with `SystemStoreBuilder::in_stage`.
+    pub fn add_stage(&mut self, name: &str) {
+        self.system_store.add_stage(name);
+    }
+
     /// Removes the given `entity`.
     pub fn remove_system(&mut self, system_id: u32) {
         self.system_store.remove_system(system_id);
     }
 
+    /// Removes all systems except the init and cleanup system. New systems get ids after the
+    /// removed ones.
+    pub fn clear_systems(&mut self) {
+        self.system_store.clear();
+    }
+
+    /// Enables or disables the system with the given `system_id`. Disabled systems are skipped
+    /// by `run` but keep their id and priority.
+    pub fn set_system_enabled(&mut self, system_id: u32, enabled: bool) {
+        self.system_store.set_enabled(system_id, enabled);
+    }
+
+    /// Returns the id, priority and enabled state of all systems sorted by id.
+    pub fn systems(&self) -> Vec<(u32, Priority, bool)> {
+        self.system_store.systems()
+    }
+
     /// Borrows mutable the entity component manager.
     pub fn entity_component_manager(&mut self) -> &mut EntityComponentManager<E, C> {
         &mut self.entity_component_manager
     }
 
+    /// Registers the component type `T` so it could be copied by `snapshot`.
+    pub fn register_clone_component<T: Component + Clone>(&mut self) {
+        self.clone_registry.register::<T>();
+    }
+
+    /// Deep copies the entities, components and the entity counter. Systems are not part of
+    /// the snapshot. Fails with `NotFound::Component` if a stored component type is not
+    /// registered with `register_clone_component`.
+    pub fn snapshot(&self) -> Result<WorldSnapshot<E, C>, NotFound>
+    where
+        E: Clone,
+        C: CloneComponentStore,
+    {
+        let (entity_store, component_store) = self.entity_component_manager.stores();
+
+        Ok(WorldSnapshot {
+            entity_store: entity_store.clone(),
+            component_store: component_store.clone_with_registry(&self.clone_registry)?,
+            entity_counter: self.entity_component_manager.entity_counter(),
+        })
+    }
+
+    /// Rolls the entities, components and the entity counter back to the given `snapshot`.
+    /// Queued commands are dropped.
+    pub fn restore(&mut self, snapshot: WorldSnapshot<E, C>) {
+        self.entity_component_manager.restore(
+            snapshot.entity_store,
+            snapshot.component_store,
+            snapshot.entity_counter,
+        );
+    }
+
     /// Print infos about the given entity.
+    #[cfg(not(feature = "no_std"))]
     pub fn print_entity(&self, entity: impl Into<Entity>) {
-        self.entity_component_manager.component_store().print_entity(entity);
+        self.entity_component_manager
+            .component_store()
+            .print_entity(entity.into());
+    }
+
+    /// Registers the name of the component type `T` that is used by `dump` for component stores
+    /// that key components by type.
+    pub fn register_type_name<T: Component>(&mut self, name: &'static str) {
+        self.type_names.register_name::<T>(name);
+    }
+
+    /// Returns a description of all entities with their owned components and shared links,
+    /// sorted by entity id.
+    pub fn dump(&self) -> String {
+        let (entity_store, component_store) = self.entity_component_manager.stores();
+        let mut entities = entity_store.entities();
+        entities.sort();
+
+        entities
+            .iter()
+            .map(|entity| {
+                format!(
+                    "{:?}\n{}",
+                    entity,
+                    component_store.describe_entity(*entity, &self.type_names)
+                )
+            })
+            .collect()
+    }
+
+    /// Prints all entities with their owned components and shared links.
+    #[cfg(not(feature = "no_std"))]
+    pub fn print_all(&self) {
+        print!("{}", self.dump());
+    }
+
+    /// Returns the ids of the systems in the order `run` executes them.
+    pub fn execution_order(&self) -> Vec<u32> {
+        self.system_store.execution_order()
     }
 
-    /// Run all systems of the world.
+    /// Validates the schedule without running any system and returns the ids in execution
+    /// order. Fails if a priority references a removed system or on a dependency cycle.
+    pub fn dry_run(&self) -> Result<Vec<u32>, NotFound> {
+        self.system_store.dry_run()
+    }
+
+    /// Pauses the world. While paused `run` and `run_from_priority` do nothing, not even the
+    /// init system runs. Unlike `set_system_enabled` this affects all systems.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes a paused world.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Returns the current tick. It starts with 0 and advances after each `run` of the world.
+    /// Component stores with change detection mark changed components with it.
+    pub fn current_tick(&self) -> Tick {
+        self.tick
+    }
+
+    /// Returns `true` if the world is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Marks the world as not run yet, so the next `run` or `run_init` executes the init system
+    /// again, e.g. to reload a scene. Entities are kept, call `clear_entities` before to start
+    /// from an empty world.
+    pub fn reset(&mut self) {
+        self.first_run = true;
+    }
+
+    /// Runs the init system. `run` calls it on its first call if it was not called before. If
+    /// the init system already ran `NotFound::AlreadyInitialized` will be returned.
+    pub fn run_init(&mut self) -> Result<(), NotFound> {
+        if !self.first_run {
+            return Err(NotFound::AlreadyInitialized);
+        }
+
+        self.run_init_system();
+        Ok(())
+    }
+
+    /// Runs the cleanup system. Otherwise it runs when the world is dropped. If the cleanup
+    /// system already ran `NotFound::AlreadyCleanedUp` will be returned.
+    pub fn cleanup(&mut self) -> Result<(), NotFound> {
+        if self.cleaned_up {
+            return Err(NotFound::AlreadyCleanedUp);
+        }
+
+        self.run_cleanup_system();
+        self.cleaned_up = true;
+        Ok(())
+    }
+
+    fn run_init_system(&mut self) {
+        if let Some(init_system) = self.system_store.borrow_init_system() {
+            init_system.system.run(&mut self.entity_component_manager);
+            self.entity_component_manager.apply_commands();
+        }
+        self.first_run = false;
+    }
+
+    fn run_cleanup_system(&mut self) {
+        if let Some(cleanup_system) = self.system_store.borrow_cleanup_system() {
+            cleanup_system
+                .system
+                .run(&mut self.entity_component_manager);
+
+            // there is no later run on drop, so the commands of the cleanup system are applied
+            // right away
+            self.entity_component_manager.apply_commands();
+        }
+    }
+
+    /// Run all systems of the world. The systems run one after another, because each of them
+    /// borrows the whole `EntityComponentManager` mutably.
     pub fn run(&mut self) {
-        if self.first_run {
-            if let Some(init_system) = self.system_store.borrow_init_system() {
-                init_system.system.run(&mut self.entity_component_manager);
+        self.run_systems(|_| true);
+    }
+
+    /// Runs all systems of the world `iterations` times. The init system only runs on the first
+    /// run of the world.
+    pub fn run_n(&mut self, iterations: usize) {
+        for _ in 0..iterations {
+            self.run();
+        }
+    }
+
+    /// Runs all systems of the world until the `predicate` returns `true` after a run, but at
+    /// most `max_iters` times. Returns the number of runs.
+    pub fn run_until(
+        &mut self,
+        max_iters: usize,
+        predicate: impl Fn(&EntityComponentManager<E, C>) -> bool,
+    ) -> usize {
+        for iteration in 1..=max_iters {
+            self.run();
+
+            if predicate(&self.entity_component_manager) {
+                return iteration;
             }
-            self.first_run = false;
         }
 
-        let priorities = &self.system_store.priorities;
-        for priority in priorities.values() {
-            for system in priority {
-                self.system_store
-                    .borrow_entity_system(*system)
-                    .unwrap()
-                    .system
-                    .run(&mut self.entity_component_manager);
+        max_iters
+    }
+
+    /// Like `run`, but only runs the systems with a priority of at least `min`. Useful to run
+    /// only critical systems under load.
+    pub fn run_from_priority(&mut self, min: Priority) {
+        self.run_systems(|priority| priority >= min);
+    }
+
+    fn run_systems(&mut self, filter: impl Fn(Priority) -> bool) {
+        if self.paused {
+            return;
+        }
+
+        if self.first_run {
+            self.run_init_system();
+        }
+
+        for system in self.system_store.cached_execution_order().iter() {
+            let entity_system = self.system_store.borrow_entity_system(*system).unwrap();
+
+            if entity_system.is_enabled() && filter(entity_system.priority()) {
+                for _ in 0..entity_system.sub_steps() {
+                    if entity_system.has_filter() || entity_system.has_sort() {
+                        let (entity_store, component_store) =
+                            self.entity_component_manager.stores();
+                        let entities = entity_system.entities(entity_store, component_store);
+                        self.entity_component_manager
+                            .set_system_entities(Some(entities));
+                    }
+
+                    entity_system.system.run(&mut self.entity_component_manager);
+                    self.entity_component_manager.set_system_entities(None);
+                    self.entity_component_manager.apply_commands();
+                }
             }
         }
+
+        self.tick += 1;
+        self.entity_component_manager
+            .component_store_mut()
+            .set_tick(self.tick);
+    }
+}
+
+impl<E, C> World<E, C>
+where
+    E: EntityStore + 'static,
+    C: ManageEntities + 'static,
+{
+    /// Removes all entities with their components but keeps the allocated capacity. Removal
+    /// hooks run for each entity. If `reset_counter` is `true` the next created entity starts
+    /// with id 0 again. The init system does not run again, call `reset` for that.
+    pub fn clear_entities(&mut self, reset_counter: bool) {
+        self.entity_component_manager.clear(reset_counter);
+    }
+
+    /// Checks if all entities with components are registered in the entity store. If not, the
+    /// entities that only the component store knows are returned sorted by id. Entities without
+    /// components are valid.
+    pub fn validate(&self) -> Result<(), Vec<Entity>> {
+        let (entity_store, component_store) = self.entity_component_manager.stores();
+        let entities: BTreeSet<Entity> = entity_store.entities().into_iter().collect();
+        let component_entities: BTreeSet<Entity> = component_store.entities().into_iter().collect();
+
+        let drift: Vec<Entity> = component_entities.difference(&entities).copied().collect();
+
+        if drift.is_empty() {
+            return Ok(());
+        }
+
+        Err(drift)
+    }
+}
+
+impl<E> World<E, StringComponentStore>
+where
+    E: EntityStore + 'static,
+{
+    /// Returns each entity that has all of the given `keys` together with its resolved
+    /// components in the order of `keys`. Entities missing a key are skipped. It gathers the data
+    /// for custom serializers or network snapshots.
+    pub fn export_entities<'a>(
+        &self,
+        keys: &[&'a str],
+    ) -> Vec<(Entity, ExportedComponents<'a, '_>)> {
+        let (entity_store, component_store) = self.entity_component_manager.stores();
+
+        entity_store
+            .entities()
+            .into_iter()
+            .filter_map(|entity| {
+                keys.iter()
+                    .map(|key| component_store.get_dyn(key, entity).map(|c| (*key, c)))
+                    .collect::<Result<Vec<_>, _>>()
+                    .ok()
+                    .map(|components| (entity, components))
+            })
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::component::TypeComponentStore;
+    use crate::component::{TypeComponentBuilder, TypeComponentStore};
     use crate::entity::{Entity, VecEntityStore};
+    use std::rc::Rc;
 
     #[derive(Default)]
     struct TestSystem;
 
+    struct CounterSystem;
+
+    impl System<VecEntityStore, TypeComponentStore> for CounterSystem {
+        fn run(&self, ecm: &mut EntityComponentManager<VecEntityStore, TypeComponentStore>) {
+            ecm.for_each_entity_mut(|entity, store| {
+                if let Ok(counter) = store.get_mut::<u32>(entity) {
+                    *counter += 1;
+                }
+            });
+        }
+    }
+
+    struct RemoveSystem;
+
+    impl System<VecEntityStore, TypeComponentStore> for RemoveSystem {
+        fn run(&self, ecm: &mut EntityComponentManager<VecEntityStore, TypeComponentStore>) {
+            let (e_store, c_store) = ecm.stores();
+
+            for entity in &e_store.inner {
+                if c_store.contains_component::<String>(*entity) {
+                    ecm.commands().remove_entity(*entity);
+                }
+            }
+        }
+    }
+
+    struct NameSystem;
+
+    impl System<VecEntityStore, TypeComponentStore> for NameSystem {
+        fn run(&self, ecm: &mut EntityComponentManager<VecEntityStore, TypeComponentStore>) {
+            let (e_store, c_store) = ecm.stores();
+
+            for entity in &e_store.inner {
+                if let Ok(counter) = c_store.get::<u32>(*entity) {
+                    ecm.commands()
+                        .add_component(*entity, format!("Counter {}", counter));
+                }
+            }
+        }
+    }
+
+    struct RecordSystem(u32, Rc<RefCell<Vec<u32>>>);
+
+    impl System<VecEntityStore, TypeComponentStore> for RecordSystem {
+        fn run(&self, _ecm: &mut EntityComponentManager<VecEntityStore, TypeComponentStore>) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
     impl System<VecEntityStore, TypeComponentStore> for TestSystem {
         fn run(&self, _ecm: &mut EntityComponentManager<VecEntityStore, TypeComponentStore>) {}
     }
@@ -147,6 +546,19 @@ mod tests {
         assert_eq!(Entity(1), world.create_entity().build());
     }
 
+    #[test]
+    fn create_entities() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        world.create_entity().build();
+
+        let entities = world.create_entities(3);
+
+        assert_eq!(entities, vec![Entity(1), Entity(2), Entity(3)]);
+        let registered = world.entity_component_manager().stores().0.entities();
+        assert!(entities.iter().all(|e| registered.contains(e)));
+    }
+
     #[test]
     fn create_system() {
         let mut world =
@@ -154,4 +566,867 @@ mod tests {
         assert_eq!(0, world.create_system(TestSystem).build());
         assert_eq!(1, world.create_system(TestSystem).build());
     }
+
+    #[test]
+    fn validate() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let entity = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(5_u32).build())
+            .build();
+
+        assert_eq!(world.validate(), Ok(()));
+
+        world
+            .entity_component_manager()
+            .component_store_mut()
+            .register(Entity(7), 5_u32);
+        world.entity_component_manager().register_entity(Entity(9));
+
+        world
+            .entity_component_manager()
+            .component_store_mut()
+            .register(Entity(3), 5_u32);
+
+        assert_eq!(world.validate(), Err(vec![Entity(3), Entity(7)]));
+
+        world.remove_entity(Entity(3));
+        world.remove_entity(Entity(7));
+        world.remove_entity(Entity(9));
+        assert_eq!(world.validate(), Ok(()));
+        assert_eq!(
+            world.entity_component_manager().stores().0.entities(),
+            vec![entity]
+        );
+    }
+
+    #[test]
+    fn validate_blank_entities() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        world.create_entities(2);
+        world.create_entity().build();
+
+        assert_eq!(world.validate(), Ok(()));
+    }
+
+    #[test]
+    fn remove_system() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let record = Rc::new(RefCell::new(vec![]));
+        let first = world.create_system(RecordSystem(0, record.clone())).build();
+        let second = world.create_system(RecordSystem(1, record.clone())).build();
+        let third = world.create_system(RecordSystem(2, record.clone())).build();
+
+        world.remove_system(second);
+        world.run();
+
+        assert_eq!(*record.borrow(), vec![first, third]);
+    }
+
+    #[test]
+    fn remove_system_same_priority() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let record = Rc::new(RefCell::new(vec![]));
+        let first = world
+            .create_system(RecordSystem(0, record.clone()))
+            .with_priority(1)
+            .build();
+        let second = world
+            .create_system(RecordSystem(1, record.clone()))
+            .with_priority(1)
+            .build();
+
+        world.remove_system(first);
+        world.run();
+
+        assert_eq!(*record.borrow(), vec![second]);
+    }
+
+    #[test]
+    fn set_system_enabled() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let entity = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(0_u32).build())
+            .build();
+        let system = world.create_system(CounterSystem).build();
+
+        world.set_system_enabled(system, false);
+        world.run();
+        assert_eq!(
+            *world
+                .entity_component_manager()
+                .component_store()
+                .get::<u32>(entity)
+                .unwrap(),
+            0
+        );
+
+        world.set_system_enabled(system, true);
+        world.run();
+        assert_eq!(
+            *world
+                .entity_component_manager()
+                .component_store()
+                .get::<u32>(entity)
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn systems() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        world.create_system(TestSystem).with_priority(3).build();
+        world.create_system(TestSystem).build();
+        world.create_system(TestSystem).with_priority(-1).build();
+        world.set_system_enabled(1, false);
+
+        assert_eq!(
+            world.systems(),
+            vec![(0, 3, true), (1, 0, false), (2, -1, true)]
+        );
+    }
+
+    #[test]
+    fn system_dependencies() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let record = Rc::new(RefCell::new(vec![]));
+        let a = world
+            .create_system(RecordSystem(0, record.clone()))
+            .with_priority(2)
+            .build();
+        let b = world
+            .create_system(RecordSystem(1, record.clone()))
+            .with_priority(1)
+            .after(a)
+            .build();
+        let c = world
+            .create_system(RecordSystem(2, record.clone()))
+            .after(b)
+            .build();
+
+        world.run();
+
+        assert_eq!(*record.borrow(), vec![a, b, c]);
+        assert!(world
+            .create_system(TestSystem)
+            .after(c)
+            .before(a)
+            .try_build()
+            .is_err());
+        assert_eq!(world.execution_order(), vec![a, b, c]);
+    }
+
+    #[test]
+    fn stages() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let record = Rc::new(RefCell::new(vec![]));
+        world.add_stage("update");
+        world.add_stage("render");
+        let render = world
+            .create_system(RecordSystem(0, record.clone()))
+            .with_priority(-5)
+            .in_stage("render")
+            .build();
+        let update = world
+            .create_system(RecordSystem(1, record.clone()))
+            .with_priority(10)
+            .in_stage("update")
+            .build();
+
+        world.run();
+
+        assert_eq!(*record.borrow(), vec![update, render]);
+    }
+
+    #[test]
+    fn sub_steps() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let entity = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(0_u32).build())
+            .build();
+        let record = Rc::new(RefCell::new(vec![]));
+        world.create_system(CounterSystem).sub_steps(3).build();
+        world.create_system(RecordSystem(1, record.clone())).build();
+
+        world.run();
+        world.run();
+
+        assert_eq!(
+            *world
+                .entity_component_manager()
+                .component_store()
+                .get::<u32>(entity)
+                .unwrap(),
+            6
+        );
+        assert_eq!(record.borrow().len(), 2);
+    }
+
+    #[test]
+    fn commands() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let keep = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(0_u32).build())
+            .build();
+        let remove = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(String::new()).build())
+            .build();
+        world.create_system(RemoveSystem).build();
+
+        world.run();
+
+        assert_eq!(
+            world.entity_component_manager().entity_store().inner,
+            vec![keep]
+        );
+        assert!(!world
+            .entity_component_manager()
+            .component_store()
+            .contains_entity(remove));
+    }
+
+    #[test]
+    fn component_commands() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let entity = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(5_u32).build())
+            .build();
+        world.create_system(NameSystem).build();
+
+        world.run();
+
+        assert_eq!(
+            world
+                .entity_component_manager()
+                .component_store()
+                .get::<String>(entity)
+                .unwrap(),
+            "Counter 5"
+        );
+    }
+
+    #[test]
+    fn execution_order() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        world.create_system(TestSystem).with_priority(2).build();
+        world.create_system(TestSystem).with_priority(0).build();
+        world.create_system(TestSystem).with_priority(-1).build();
+        world.create_system(TestSystem).with_priority(0).build();
+
+        assert_eq!(world.execution_order(), vec![2, 1, 3, 0]);
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        world.register_clone_component::<u32>();
+        let entity = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(5_u32).build())
+            .build();
+
+        let snapshot = world.snapshot().unwrap();
+
+        *world
+            .entity_component_manager()
+            .component_store_mut()
+            .get_mut::<u32>(entity)
+            .unwrap() = 8;
+        world.create_entity().build();
+
+        world.restore(snapshot);
+
+        let ecm = world.entity_component_manager();
+        assert_eq!(*ecm.component_store().get::<u32>(entity).unwrap(), 5);
+        assert_eq!(ecm.stores().0.entities(), vec![entity]);
+        assert_eq!(world.create_entity().build(), Entity(1));
+    }
+
+    #[test]
+    fn snapshot_unregistered() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(5_u32).build())
+            .build();
+
+        assert_eq!(
+            world.snapshot().err(),
+            Some(NotFound::Component(core::any::TypeId::of::<u32>()))
+        );
+    }
+
+    #[test]
+    fn drain_entities() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let entities: Vec<Entity> = (0..3)
+            .map(|i| {
+                world
+                    .create_entity()
+                    .components(TypeComponentBuilder::new().with(i as u32).build())
+                    .build()
+            })
+            .collect();
+        let removed = Rc::new(RefCell::new(vec![]));
+        let hook_removed = removed.clone();
+        world
+            .entity_component_manager()
+            .on_remove_entity(move |entity| hook_removed.borrow_mut().push(entity));
+
+        assert_eq!(world.drain_entities(), entities);
+        assert_eq!(*removed.borrow(), entities);
+
+        let (entity_store, component_store) = world.entity_component_manager().stores();
+        assert!(entity_store.entities().is_empty());
+        assert!(component_store.is_empty());
+    }
+
+    #[test]
+    fn dump() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        world.register_type_name::<u32>("Counter");
+        let source = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(5_u32).build())
+            .build();
+        let target = world
+            .create_entity()
+            .components(
+                TypeComponentBuilder::new()
+                    .with(String::from("Button"))
+                    .with_shared::<u32>(source)
+                    .build(),
+            )
+            .build();
+
+        let dump = world.dump();
+
+        assert!(dump.contains(&format!("{:?}\n    owns Counter\n", source)));
+        assert!(dump.contains(&format!("{:?}\n    owns ", target)));
+        assert!(dump.contains(&format!("    shares Counter from {:?}\n", source)));
+    }
+
+    #[test]
+    fn run_from_priority() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let record = Rc::new(RefCell::new(vec![]));
+        world
+            .create_system(RecordSystem(0, record.clone()))
+            .with_priority(-1)
+            .build();
+        world
+            .create_system(RecordSystem(1, record.clone()))
+            .with_priority(2)
+            .build();
+        world
+            .create_system(RecordSystem(2, record.clone()))
+            .with_priority(1)
+            .build();
+
+        world.run_from_priority(1);
+
+        assert_eq!(*record.borrow(), vec![2, 1]);
+    }
+
+    #[test]
+    fn clear_entities() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(5_u32).build())
+            .build();
+        world.create_entity().build();
+
+        world.clear_entities(false);
+
+        let (entity_store, component_store) = world.entity_component_manager().stores();
+        assert!(entity_store.entities().is_empty());
+        assert!(component_store.is_empty());
+        assert_eq!(world.create_entity().build(), Entity(2));
+
+        world.clear_entities(true);
+        assert_eq!(world.create_entity().build(), Entity(0));
+    }
+
+    #[test]
+    fn clear_systems() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        world.create_system(TestSystem).build();
+        world.create_system(TestSystem).with_priority(1).build();
+
+        world.clear_systems();
+
+        assert!(world.systems().is_empty());
+        assert_eq!(world.create_system(TestSystem).build(), 2);
+    }
+
+    #[test]
+    fn dry_run() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let record = Rc::new(RefCell::new(vec![]));
+        world
+            .create_system(RecordSystem(0, record.clone()))
+            .with_priority(1)
+            .build();
+        let removed = world.create_system(RecordSystem(1, record.clone())).build();
+        world.create_system(RecordSystem(2, record.clone())).build();
+
+        assert_eq!(world.dry_run(), Ok(vec![1, 2, 0]));
+
+        world.remove_system(removed);
+
+        assert_eq!(world.dry_run(), Ok(vec![2, 0]));
+        assert!(record.borrow().is_empty());
+    }
+
+    struct EntitiesSystem(Rc<RefCell<Vec<Entity>>>);
+
+    impl System<VecEntityStore, TypeComponentStore> for EntitiesSystem {
+        fn run(&self, ecm: &mut EntityComponentManager<VecEntityStore, TypeComponentStore>) {
+            *self.0.borrow_mut() = ecm.system_entities();
+        }
+    }
+
+    #[test]
+    fn system_filter() {
+        struct Size(u32);
+
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let sized = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(Size(5)).build())
+            .build();
+        let other = world.create_entity().build();
+        let filtered = Rc::new(RefCell::new(vec![]));
+        let all = Rc::new(RefCell::new(vec![]));
+        world
+            .create_system(EntitiesSystem(filtered.clone()))
+            .with_filter(|entity, store| store.get::<Size>(entity).is_ok_and(|size| size.0 > 0))
+            .build();
+        world.create_system(EntitiesSystem(all.clone())).build();
+
+        world.run();
+
+        assert_eq!(*filtered.borrow(), vec![sized]);
+        assert_eq!(*all.borrow(), vec![sized, other]);
+    }
+
+    #[test]
+    fn system_sort() {
+        struct Depth(u32);
+
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let entities: Vec<Entity> = [2, 5, 1]
+            .iter()
+            .map(|depth| {
+                world
+                    .create_entity()
+                    .components(TypeComponentBuilder::new().with(Depth(*depth)).build())
+                    .build()
+            })
+            .collect();
+        let sorted = Rc::new(RefCell::new(vec![]));
+        world
+            .create_system(EntitiesSystem(sorted.clone()))
+            .with_sort(|a, b, store| {
+                let depth = |entity| store.get::<Depth>(entity).map_or(0, |depth| depth.0);
+                depth(b).cmp(&depth(a))
+            })
+            .build();
+
+        world.run();
+        assert_eq!(
+            *sorted.borrow(),
+            vec![entities[1], entities[0], entities[2]]
+        );
+
+        // the cached order is kept until the entities change
+        world
+            .entity_component_manager()
+            .component_store_mut()
+            .get_mut::<Depth>(entities[2])
+            .unwrap()
+            .0 = 10;
+        world.run();
+        assert_eq!(
+            *sorted.borrow(),
+            vec![entities[1], entities[0], entities[2]]
+        );
+
+        world.remove_entity(entities[0]);
+        world.run();
+        assert_eq!(*sorted.borrow(), vec![entities[2], entities[1]]);
+    }
+
+    #[test]
+    fn run_init() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let record = Rc::new(RefCell::new(vec![]));
+        world.register_init_system(RecordSystem(0, record.clone()));
+        world.create_system(RecordSystem(1, record.clone())).build();
+
+        assert!(world.run_init().is_ok());
+        assert_eq!(world.run_init(), Err(NotFound::AlreadyInitialized));
+        world.run();
+
+        assert_eq!(*record.borrow(), vec![0, 1]);
+    }
+
+    #[test]
+    fn cleanup() {
+        let record = Rc::new(RefCell::new(vec![]));
+        {
+            let mut world =
+                World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+            world.register_cleanup_system(RecordSystem(0, record.clone()));
+
+            assert!(world.cleanup().is_ok());
+            assert_eq!(world.cleanup(), Err(NotFound::AlreadyCleanedUp));
+        }
+
+        assert_eq!(*record.borrow(), vec![0]);
+    }
+
+    #[test]
+    fn cleanup_applies_commands_on_drop() {
+        struct DespawnSystem;
+
+        impl System<VecEntityStore, TypeComponentStore> for DespawnSystem {
+            fn run(&self, ecm: &mut EntityComponentManager<VecEntityStore, TypeComponentStore>) {
+                for entity in ecm.stores().0.entities() {
+                    ecm.commands().remove_entity(entity);
+                }
+            }
+        }
+
+        let removed = Rc::new(Cell::new(0));
+        {
+            let mut world =
+                World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+            world.create_entities(2);
+            world.register_cleanup_system(DespawnSystem);
+            let hook_removed = removed.clone();
+            world
+                .entity_component_manager()
+                .on_remove_entity(move |_| hook_removed.set(hook_removed.get() + 1));
+        }
+
+        assert_eq!(removed.get(), 2);
+    }
+
+    #[test]
+    fn contains_entity() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let entity = world.create_entity().build();
+        let removed = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(5_u32).build())
+            .build();
+        world.remove_entity(removed);
+
+        assert!(world.contains_entity(entity));
+        assert!(!world.contains_entity(removed));
+        assert!(!world.contains_entity(Entity(7)));
+    }
+
+    #[test]
+    fn retain_entities() {
+        struct Dead;
+
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let entities: Vec<Entity> = (0..4)
+            .map(|i| {
+                let mut builder = TypeComponentBuilder::new().with(i as u32);
+                if i % 2 == 1 {
+                    builder = builder.with(Dead);
+                }
+                world.create_entity().components(builder.build()).build()
+            })
+            .collect();
+        let shared = world
+            .create_entity()
+            .components(
+                TypeComponentBuilder::new()
+                    .with_shared::<u32>(entities[1])
+                    .build(),
+            )
+            .build();
+
+        world.retain_entities(|entity, store| !store.contains_component::<Dead>(entity));
+
+        assert!(world.contains_entity(entities[0]));
+        assert!(!world.contains_entity(entities[1]));
+        assert!(world.contains_entity(entities[2]));
+        assert!(!world.contains_entity(entities[3]));
+        assert!(world.contains_entity(shared));
+
+        let store = world.entity_component_manager().component_store();
+        assert_eq!(*store.get::<u32>(entities[2]).unwrap(), 2);
+        assert!(store.get::<u32>(entities[1]).is_err());
+        assert!(store.get::<u32>(shared).is_err());
+        assert_eq!(store.entities(), vec![entities[0], entities[2]]);
+    }
+
+    #[test]
+    fn pause() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let record = Rc::new(RefCell::new(vec![]));
+        world.register_init_system(RecordSystem(0, record.clone()));
+        world.create_system(RecordSystem(1, record.clone())).build();
+
+        world.pause();
+        assert!(world.is_paused());
+        world.run();
+        assert!(record.borrow().is_empty());
+
+        world.resume();
+        assert!(!world.is_paused());
+        world.run();
+        assert_eq!(*record.borrow(), vec![0, 1]);
+    }
+
+    #[test]
+    fn current_tick() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let first = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(1_u32).build())
+            .build();
+        let second = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(2_u32).build())
+            .build();
+        assert_eq!(world.current_tick(), 0);
+
+        world.run();
+        let tick = world.current_tick();
+        assert_eq!(tick, 1);
+
+        *world
+            .entity_component_manager()
+            .component_store_mut()
+            .get_mut::<u32>(second)
+            .unwrap() = 5;
+
+        let store = world.entity_component_manager().component_store();
+        let changed: Vec<(Entity, &u32)> = store.query_changed::<u32>(tick).collect();
+        assert_eq!(changed, vec![(second, &5)]);
+        assert_eq!(store.query_changed::<u32>(0).count(), 2);
+        assert!(store.get::<u32>(first).is_ok());
+    }
+
+    #[test]
+    fn export_entities() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), StringComponentStore::default());
+        let full = world
+            .create_entity()
+            .components(
+                StringComponentBuilder::new()
+                    .with("name", String::from("Button"))
+                    .with("depth", 2_u32)
+                    .build(),
+            )
+            .build();
+        world
+            .create_entity()
+            .components(
+                StringComponentBuilder::new()
+                    .with("name", String::from("Label"))
+                    .build(),
+            )
+            .build();
+        let shared = world
+            .create_entity()
+            .components(
+                StringComponentBuilder::new()
+                    .with("name", String::from("CheckBox"))
+                    .with_shared::<u32>("depth", full)
+                    .build(),
+            )
+            .build();
+
+        let export = world.export_entities(&["name", "depth"]);
+
+        assert_eq!(export.len(), 2);
+        assert_eq!(export[0].0, full);
+        assert_eq!(export[1].0, shared);
+        let (key, name) = export[1].1[0];
+        assert_eq!(key, "name");
+        assert_eq!(name.downcast_ref::<String>().unwrap(), "CheckBox");
+        assert_eq!(export[1].1[1].1.downcast_ref::<u32>(), Some(&2));
+    }
+
+    #[test]
+    fn from_stores() {
+        let mut type_world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let entity = type_world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(5_u32).build())
+            .build();
+        assert_eq!(
+            type_world
+                .entity_component_manager()
+                .component_store()
+                .get::<u32>(entity),
+            Ok(&5)
+        );
+
+        let mut string_world =
+            World::from_stores(VecEntityStore::default(), StringComponentStore::default());
+        let entity = string_world
+            .create_entity()
+            .components(StringComponentBuilder::new().with("size", 5_u32).build())
+            .build();
+        assert_eq!(
+            string_world
+                .entity_component_manager()
+                .component_store()
+                .get::<u32>("size", entity),
+            Ok(&5)
+        );
+    }
+
+    #[test]
+    fn run_n() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let entity = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(0_u32).build())
+            .build();
+        let runs = Rc::new(RefCell::new(vec![]));
+        world.register_init_system(RecordSystem(0, runs.clone()));
+        world.create_system(CounterSystem).build();
+
+        world.run_n(5);
+
+        assert_eq!(
+            world
+                .entity_component_manager()
+                .component_store()
+                .get::<u32>(entity),
+            Ok(&5)
+        );
+        assert_eq!(*runs.borrow(), vec![0]);
+    }
+
+    #[test]
+    fn create_boxed_system() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let entity = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(0_u32).build())
+            .build();
+        let systems: Vec<Box<dyn System<VecEntityStore, TypeComponentStore>>> =
+            vec![Box::new(CounterSystem), Box::new(CounterSystem)];
+        for system in systems {
+            world.create_boxed_system(system).build();
+        }
+
+        world.run();
+
+        assert_eq!(
+            world
+                .entity_component_manager()
+                .component_store()
+                .get::<u32>(entity),
+            Ok(&2)
+        );
+    }
+
+    #[test]
+    fn reset() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let runs = Rc::new(RefCell::new(vec![]));
+        world.register_init_system(RecordSystem(0, runs.clone()));
+        world.create_system(RecordSystem(1, runs.clone())).build();
+
+        world.run();
+        world.run();
+        world.clear_entities(true);
+        world.reset();
+        world.run();
+
+        assert_eq!(*runs.borrow(), vec![0, 1, 1, 0, 1]);
+    }
+
+    #[test]
+    fn entity_count() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        assert_eq!((world.entity_count(), world.is_empty()), (0, true));
+
+        let entities = world.create_entities(3);
+        assert_eq!((world.entity_count(), world.is_empty()), (3, false));
+
+        world.remove_entity(entities[1]);
+        assert_eq!(world.entity_count(), 2);
+
+        world.clear_entities(false);
+        assert!(world.is_empty());
+    }
+
+    #[test]
+    fn run_until() {
+        let mut world =
+            World::from_stores(VecEntityStore::default(), TypeComponentStore::default());
+        let entity = world
+            .create_entity()
+            .components(TypeComponentBuilder::new().with(0_u32).build())
+            .build();
+        world.create_system(CounterSystem).build();
+
+        let reached = |threshold: u32| {
+            move |ecm: &EntityComponentManager<VecEntityStore, TypeComponentStore>| {
+                ecm.component_store().get::<u32>(entity) == Ok(&threshold)
+            }
+        };
+
+        assert_eq!(world.run_until(10, reached(4)), 4);
+        assert_eq!(world.run_until(3, reached(100)), 3);
+        assert_eq!(
+            world
+                .entity_component_manager()
+                .component_store()
+                .get::<u32>(entity),
+            Ok(&7)
+        );
+    }
 }
@@ -0,0 +1,43 @@
+#![cfg(feature = "no_std")]
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::String;
+
+use dces::prelude::*;
+
+struct Counter(u32);
+
+struct CounterSystem;
+impl System<EntityStore, ComponentStore> for CounterSystem {
+    fn run(&self, ecm: &mut EntityComponentManager<EntityStore, ComponentStore>) {
+        ecm.for_each_entity_mut(|entity, store| {
+            if let Ok(counter) = store.get_mut::<Counter>(entity) {
+                counter.0 += 1;
+            }
+        });
+    }
+}
+
+#[test]
+fn no_std_world() {
+    let mut world = World::from_stores(EntityStore::default(), ComponentStore::default());
+    let entity = world
+        .create_entity()
+        .components(
+            ComponentBuilder::new()
+                .with(Counter(0))
+                .with(String::from("Counter"))
+                .build(),
+        )
+        .build();
+    world.create_system(CounterSystem).build();
+
+    world.run();
+    world.run();
+
+    let store = world.entity_component_manager().component_store();
+    assert_eq!(store.get::<Counter>(entity).unwrap().0, 2);
+    assert_eq!(store.get::<String>(entity).unwrap(), "Counter");
+}